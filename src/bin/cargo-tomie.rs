@@ -0,0 +1,19 @@
+//! `cargo tomie` entry point: cargo finds this binary on the PATH and forwards to TomieChecker.
+use std::env;
+use std::process::{exit, Command};
+
+fn main() {
+    let checker = env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("TomieChecker{}", env::consts::EXE_SUFFIX)))
+        .filter(|checker| checker.is_file())
+        .unwrap_or_else(|| "TomieChecker".into());
+
+    match Command::new(&checker).args(env::args_os().skip(1)).status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Unable to run {}: {}", checker.display(), e);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::cargo_home;
+use crate::error::TomieError;
+use crate::git::GitMirror;
+use crate::registry::HttpClient;
+use semver::Version;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// File names, without extension and case, projects keep their release notes in.
+const CHANGELOG_NAMES: &[&str] = &[
+    "changelog",
+    "changes",
+    "history",
+    "news",
+    "releases",
+    "release-notes",
+    "release_notes",
+];
+
+/// Release notes of a dependency between the version in use and the latest one.
+#[derive(Debug, Serialize)]
+pub struct Changelog {
+    pub name: String,
+    pub repository: String,
+    /// Path of the changelog in the repository.
+    pub file: String,
+    pub from: String,
+    pub to: String,
+    /// In the order the changelog lists them, usually newest first.
+    pub sections: Vec<ChangelogSection>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangelogSection {
+    pub version: String,
+    /// Markdown under the version's heading, the heading itself excluded.
+    pub text: String,
+}
+
+/// Level of a Markdown heading line, 0 for any other line.
+fn heading_level(line: &str) -> usize {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if line[level..].starts_with(' ') {
+        level
+    } else {
+        0
+    }
+}
+
+/// Version a heading names, e.g. `## [1.2.0] - 2024-01-02`, `# v1.2.0` or
+/// `### Version 1.2.0 (2024-01-02)`.
+fn heading_version(line: &str) -> Option<Version> {
+    line.trim_start_matches('#')
+        .split(|c: char| c.is_whitespace() || "[]()".contains(c))
+        .find_map(|word| Version::parse(word.trim_start_matches('v')).ok())
+}
+
+/// The sections of `text` for releases after `from` up to and including `to`. Release
+/// headings are those at the level of the first heading naming a version; anything
+/// below them, sub-headings included, belongs to the release.
+pub fn extract_sections(text: &str, from: &Version, to: &Version) -> Vec<ChangelogSection> {
+    let mut sections = Vec::new();
+    let mut release_level = None;
+    let mut current: Option<(Version, Vec<&str>)> = None;
+    let mut in_code = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let level = if in_code { 0 } else { heading_level(line) };
+        let version = (level > 0).then(|| heading_version(line)).flatten();
+        if release_level.is_none() && version.is_some() {
+            release_level = Some(level);
+        }
+
+        match release_level {
+            // A heading at the release level or above ends the release before it.
+            Some(release) if level > 0 && level <= release => {
+                sections.extend(current.take());
+                if level == release {
+                    current = version.map(|version| (version, Vec::new()));
+                }
+            }
+            _ => {
+                if let Some((_, lines)) = &mut current {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+    sections.extend(current);
+
+    sections
+        .into_iter()
+        .filter(|(version, _)| from < version && version <= to)
+        .map(|(version, lines)| ChangelogSection {
+            version: version.to_string(),
+            text: lines.join("\n").trim().to_string(),
+        })
+        .collect()
+}
+
+/// The most likely changelog of `crate_name` among the repository's `files`: one next
+/// to the crate's own directory in a workspace, else the one closest to the root.
+fn changelog_file<'a>(files: &'a [String], crate_name: &str) -> Option<&'a String> {
+    files
+        .iter()
+        .filter(|path| {
+            let file = path.rsplit('/').next().unwrap_or(path);
+            let stem = file.split('.').next().unwrap_or(file).to_ascii_lowercase();
+            CHANGELOG_NAMES.contains(&stem.as_str())
+        })
+        .min_by_key(|path| {
+            let in_crate_dir = path.split('/').any(|dir| dir == crate_name);
+            (!in_crate_dir, path.matches('/').count(), path.len())
+        })
+}
+
+/// Git URL of a `repository` field; GitHub and GitLab links often point inside the
+/// repository, e.g. `https://github.com/owner/repo/tree/main/crate`.
+fn clone_url(repository: &str) -> &str {
+    let repository = repository.trim_end_matches('/');
+    repository
+        .find("/-/tree/")
+        .or_else(|| repository.find("/tree/"))
+        .map_or(repository, |end| &repository[..end])
+}
+
+/// `package.repository` of `name` `version` as cargo unpacked it under
+/// `$CARGO_HOME/registry/src`.
+fn local_repository(name: &str, version: &Version) -> Option<String> {
+    let src_dir = cargo_home()?.join("registry").join("src");
+    fs::read_dir(src_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find_map(|entry| {
+            let manifest = entry
+                .path()
+                .join(format!("{}-{}", name, version))
+                .join("Cargo.toml");
+            let content = fs::read_to_string(manifest).ok()?;
+            let manifest: toml::Value = toml::from_str(&content).ok()?;
+            manifest
+                .get("package")?
+                .get("repository")?
+                .as_str()
+                .map(String::from)
+        })
+}
+
+/// The repository of `name`: from the copy of `version` cargo unpacked, else from the
+/// crate's metadata on the web API at `api_url` unless `offline`.
+pub async fn find_repository(
+    http: &HttpClient,
+    api_url: &str,
+    name: &str,
+    version: &Version,
+    offline: bool,
+) -> Result<Option<String>, TomieError> {
+    if let Some(repository) = local_repository(name, version) {
+        log::debug!("Repository of {} from the local registry copy", name);
+        return Ok(Some(repository));
+    }
+    if offline {
+        return Ok(None);
+    }
+    let url = format!("{}/api/v1/crates/{}", api_url.trim_end_matches('/'), name);
+    let json = http.get_json(name, &url).await?;
+    Ok(json["crate"]["repository"].as_str().map(String::from))
+}
+
+/// Finds the changelog in the mirror of `repository` under `mirrors` and extracts
+/// the releases of `name` after `from` up to `to`.
+pub fn load_changelog(
+    mirrors: &Path,
+    name: &str,
+    repository: &str,
+    from: &Version,
+    to: &Version,
+    offline: bool,
+) -> Result<Changelog, TomieError> {
+    let url = clone_url(repository);
+    let mirror = GitMirror::open(mirrors, url, offline)?;
+    let files = mirror.files()?;
+    let Some(file) = changelog_file(&files, name) else {
+        return Err(TomieError::Git {
+            url: url.to_string(),
+            reason: "no changelog found in the repository".to_string(),
+        });
+    };
+    log::debug!("Changelog of {}: {}", name, file);
+    let text = mirror.read_file(file)?;
+    Ok(Changelog {
+        name: name.to_string(),
+        repository: url.to_string(),
+        file: file.clone(),
+        from: from.to_string(),
+        to: to.to_string(),
+        sections: extract_sections(&text, from, to),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "# Changelog
+
+All notable changes.
+
+## [Unreleased]
+- pending work
+
+## [2.1.0] - 2026-05-01
+### Added
+- `Foo::bar`
+
+```rust
+## 9.9.9 is not a heading in here
+```
+
+## v2.0.0 (2026-01-01)
+### Changed
+- renamed `baz` to `qux`
+
+## 1.2.0
+- faster
+
+## 1.0.0
+- first
+";
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    fn versions(sections: &[ChangelogSection]) -> Vec<&str> {
+        sections.iter().map(|s| s.version.as_str()).collect()
+    }
+
+    #[test]
+    fn sections_after_from_up_to_to() {
+        let sections = extract_sections(CHANGELOG, &version("1.2.0"), &version("2.1.0"));
+        assert_eq!(versions(&sections), ["2.1.0", "2.0.0"]);
+        assert_eq!(
+            sections[0].text,
+            "### Added\n- `Foo::bar`\n\n```rust\n## 9.9.9 is not a heading in here\n```"
+        );
+        assert_eq!(sections[1].text, "### Changed\n- renamed `baz` to `qux`");
+
+        let sections = extract_sections(CHANGELOG, &version("1.0.0"), &version("2.0.0"));
+        assert_eq!(versions(&sections), ["2.0.0", "1.2.0"]);
+
+        let sections = extract_sections(CHANGELOG, &version("2.1.0"), &version("2.1.0"));
+        assert!(sections.is_empty());
+    }
+
+    #[test]
+    fn unversioned_heading_ends_a_release() {
+        let text = "# 1.1.0\n- fix\n# Older releases\nSee the wiki.\n# 1.0.0\n- first\n";
+        let sections = extract_sections(text, &version("0.1.0"), &version("1.1.0"));
+        assert_eq!(versions(&sections), ["1.1.0", "1.0.0"]);
+        assert_eq!(sections[0].text, "- fix");
+    }
+
+    #[test]
+    fn deeper_version_headings_belong_to_their_release() {
+        let text = "## 0.3.0\n### Bumped\n#### serde 1.0.200\n- why\n## 0.2.0\n- old\n";
+        let sections = extract_sections(text, &version("0.2.0"), &version("0.3.0"));
+        assert_eq!(versions(&sections), ["0.3.0"]);
+        assert_eq!(sections[0].text, "### Bumped\n#### serde 1.0.200\n- why");
+    }
+
+    #[test]
+    fn changelog_next_to_the_crate_wins() {
+        let files = [
+            "CHANGELOG.md",
+            "crates/foo/CHANGELOG.md",
+            "crates/bar/CHANGES.md",
+            "docs/changelog/index.md",
+            "src/lib.rs",
+        ]
+        .map(String::from);
+        assert_eq!(
+            changelog_file(&files, "foo").map(String::as_str),
+            Some("crates/foo/CHANGELOG.md")
+        );
+        assert_eq!(
+            changelog_file(&files, "baz").map(String::as_str),
+            Some("CHANGELOG.md")
+        );
+        assert_eq!(changelog_file(&files[4..], "foo"), None);
+    }
+
+    #[test]
+    fn clone_url_drops_tree_links() {
+        assert_eq!(
+            clone_url("https://github.com/owner/repo/tree/main/crates/foo"),
+            "https://github.com/owner/repo"
+        );
+        assert_eq!(
+            clone_url("https://gitlab.com/owner/repo/-/tree/main"),
+            "https://gitlab.com/owner/repo"
+        );
+        assert_eq!(
+            clone_url("https://github.com/owner/repo/"),
+            "https://github.com/owner/repo"
+        );
+    }
+}
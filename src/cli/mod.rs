@@ -0,0 +1,159 @@
+use crate::registry::{Protocol, CRATES_IO_API, DEFAULT_RETRY_STATUSES, DEFAULT_USER_AGENT};
+use crate::utils::{FailOn, OutputFormat};
+use clap::{Args, Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "cargo-tomie",
+    bin_name = "cargo tomie",
+    version,
+    about = "Checks the dependencies of a Cargo manifest against the registry"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub global: GlobalArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Path to the Cargo.toml to analyze; a workspace root analyzes every member.
+    #[arg(long, global = true, value_name = "PATH", default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+    /// Output format of the report.
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Only print the report and errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Print diagnostics while analyzing; repeat for more detail (see also TOMIE_LOG).
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Never touch the network; read versions from cargo's local registry index.
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Registry for dependencies without a `registry` key [default: from cargo config, else crates-io].
+    #[arg(long, global = true, value_name = "NAME")]
+    pub registry: Option<String>,
+    /// Consider pre-releases when picking the newest version.
+    #[arg(long, global = true)]
+    pub include_prerelease: bool,
+    /// Seconds to wait for a connection to the registry.
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 10)]
+    pub connect_timeout: u64,
+    /// Seconds a single registry request may take, connection included.
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 30)]
+    pub timeout: u64,
+    /// User-Agent sent to the registry; crates.io expects contact information in it.
+    #[arg(long, global = true, env = "TOMIE_USER_AGENT", default_value = DEFAULT_USER_AGENT)]
+    pub user_agent: String,
+    /// Registry lookups in flight at the same time.
+    #[arg(short, long, global = true, value_name = "N", default_value_t = 4)]
+    pub jobs: usize,
+    /// How to fetch version metadata from the registry.
+    #[arg(long, global = true, value_enum, default_value_t = Protocol::Sparse)]
+    pub protocol: Protocol,
+    /// Average registry requests per second, 0 for no limit
+    /// [default: 1 for the web API, no limit for the sparse index].
+    #[arg(long, global = true, value_name = "N")]
+    pub rate: Option<f64>,
+    /// Requests that may be sent back to back before the rate applies.
+    #[arg(long, global = true, value_name = "N", default_value_t = 1)]
+    pub burst: u32,
+    /// Attempts per registry lookup before giving up, the first one included.
+    #[arg(long, global = true, value_name = "N", default_value_t = 3)]
+    pub retries: u32,
+    /// Initial delay between attempts in milliseconds, doubled on every retry.
+    #[arg(long, global = true, value_name = "MS", default_value_t = 500)]
+    pub retry_backoff: u64,
+    /// HTTP status codes treated as transient.
+    #[arg(
+        long,
+        global = true,
+        value_name = "CODE",
+        value_delimiter = ',',
+        default_values_t = DEFAULT_RETRY_STATUSES
+    )]
+    pub retry_status: Vec<u16>,
+    /// Seconds a cached registry response is used without asking the registry again.
+    #[arg(long, global = true, value_name = "SECS", default_value_t = 3600)]
+    pub cache_ttl: u64,
+    /// Revalidate every cached registry response, whatever its age.
+    #[arg(long, global = true, conflicts_with = "no_cache")]
+    pub refresh: bool,
+    /// Neither read nor write the registry cache.
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+    /// Directory of the registry cache [default: tomie under the user cache directory].
+    #[arg(long, global = true, value_name = "DIR", env = "TOMIE_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Answer every lookup from a JSON file mapping crate names to their versions
+    /// (`num`, `yanked`, ...), instead of any registry.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub registry_fixture: Option<PathBuf>,
+    /// Exit with a non-zero code when findings reach this severity:
+    /// 3 for outdated, 4 for breaking updates, 5 for analysis errors.
+    #[arg(long, global = true, value_enum, default_value_t = FailOn::Error)]
+    pub fail_on: FailOn,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Report outdated dependencies (the default).
+    Check,
+    /// Rewrite the requirements of outdated dependencies, keeping the manifests'
+    /// formatting; prints a unified diff unless `--write` is given.
+    Update {
+        /// Only update these crates; every outdated crate when empty.
+        crates: Vec<String>,
+        /// Also propose updates outside of the current requirement.
+        #[arg(long)]
+        breaking: bool,
+        /// Write the new requirements to the manifests instead of printing a diff.
+        #[arg(long)]
+        write: bool,
+    },
+    /// Print the manifest changes `update` would make as a Markdown review comment:
+    /// a summary table and a unified diff. Never writes anything.
+    Diff {
+        /// Only update these crates; every outdated crate when empty.
+        crates: Vec<String>,
+        /// Also propose updates outside of the current requirement.
+        #[arg(long)]
+        breaking: bool,
+    },
+    /// Pick in the terminal which outdated dependencies to update, and to which
+    /// version, then write the selection to the manifests.
+    Interactive,
+    /// Show everything known about one dependency, with the changelog sections of the
+    /// releases between the version in use and the latest one when it is outdated.
+    Explain {
+        /// Name of the dependency as written in the manifest.
+        name: String,
+        /// Do not look up the changelog.
+        #[arg(long)]
+        no_changelog: bool,
+        /// Git repository to read the changelog from, instead of the crate's
+        /// `repository` metadata; a local path works too.
+        #[arg(long, value_name = "URL")]
+        repository: Option<String>,
+        /// Web API the crate's `repository` metadata is read from when cargo has no
+        /// local copy of the crate.
+        #[arg(long, value_name = "URL", default_value = CRATES_IO_API)]
+        api_url: String,
+    },
+}
+
+impl Cli {
+    /// Parses the command line, dropping the extra `tomie` argument cargo passes to subcommands.
+    pub fn parse_args() -> Self {
+        let mut args: Vec<OsString> = std::env::args_os().collect();
+        if args.get(1).is_some_and(|arg| arg == "tomie") {
+            args.remove(1);
+        }
+        Cli::parse_from(args)
+    }
+}
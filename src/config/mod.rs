@@ -0,0 +1,394 @@
+use crate::error::TomieError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CRATES_IO: &str = "crates-io";
+const CRATES_IO_GIT_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
+
+/// The parts of cargo's configuration that decide where crates are looked up.
+#[derive(Debug, Default, Deserialize)]
+pub struct CargoConfig {
+    #[serde(default)]
+    pub registries: BTreeMap<String, RegistryConfig>,
+    #[serde(default)]
+    pub source: BTreeMap<String, SourceConfig>,
+    #[serde(default)]
+    pub registry: DefaultRegistryConfig,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RegistryConfig {
+    pub index: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceConfig {
+    pub replace_with: Option<String>,
+    pub registry: Option<String>,
+    /// Crates unpacked by `cargo vendor`.
+    pub directory: Option<String>,
+    pub local_registry: Option<String>,
+}
+
+impl SourceConfig {
+    /// Where the crates of a `directory` or `local-registry` source are on disk.
+    fn local_path(&self) -> Option<&str> {
+        self.directory.as_deref().or(self.local_registry.as_deref())
+    }
+}
+
+/// The `[registry]` table: the default registry. Its crates.io token is never read;
+/// reading crates.io needs none.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct DefaultRegistryConfig {
+    pub default: Option<String>,
+}
+
+/// Where the index of a registry lives, once source replacement is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexLocation {
+    CratesIo,
+    Sparse(String),
+    Git(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedRegistry {
+    pub name: String,
+    pub index: IndexLocation,
+    /// Token of a `[registries]` entry; never set for crates.io.
+    pub token: Option<String>,
+}
+
+impl CargoConfig {
+    /// Reads every `.cargo/config.toml` from `dir` up to the filesystem root, then
+    /// `$CARGO_HOME/config.toml` and `credentials.toml`; the closest file wins.
+    pub fn load(dir: &Path) -> Result<Self, TomieError> {
+        // A relative `dir`, e.g. `.`, would stop short of its parents.
+        let dir = dir
+            .canonicalize()
+            .map_err(|source| TomieError::ManifestIo {
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        let mut files = Vec::new();
+        for ancestor in dir.ancestors() {
+            files.extend(config_file(&ancestor.join(".cargo")));
+        }
+        if let Some(home) = cargo_home() {
+            files.extend(config_file(&home));
+            files.extend(existing(home.join("credentials.toml")));
+            files.extend(existing(home.join("credentials")));
+        }
+
+        let mut config = CargoConfig::default();
+        for path in files.iter().rev() {
+            log::debug!("Reading cargo configuration {}", path.display());
+            let content = fs::read_to_string(path).map_err(|source| TomieError::ManifestIo {
+                path: path.clone(),
+                source,
+            })?;
+            let layer: CargoConfig =
+                toml::from_str(&content).map_err(|source| TomieError::TomlParse {
+                    path: path.clone(),
+                    source,
+                })?;
+            config.merge(layer);
+        }
+        Ok(config)
+    }
+
+    fn merge(&mut self, layer: CargoConfig) {
+        for (name, registry) in layer.registries {
+            let entry = self.registries.entry(name).or_default();
+            entry.index = registry.index.or(entry.index.take());
+            entry.token = registry.token.or(entry.token.take());
+        }
+        for (name, source) in layer.source {
+            let entry = self.source.entry(name).or_default();
+            entry.replace_with = source.replace_with.or(entry.replace_with.take());
+            entry.registry = source.registry.or(entry.registry.take());
+            entry.directory = source.directory.or(entry.directory.take());
+            entry.local_registry = source.local_registry.or(entry.local_registry.take());
+        }
+        self.registry.default = layer.registry.default.or(self.registry.default.take());
+    }
+
+    /// Registry used by dependencies without a `registry` key.
+    pub fn default_registry(&self) -> String {
+        env::var("CARGO_REGISTRY_DEFAULT")
+            .ok()
+            .or_else(|| self.registry.default.clone())
+            .unwrap_or_else(|| CRATES_IO.to_string())
+    }
+
+    /// Follows `[source]` replacements from the registry `name` to the index actually queried.
+    /// Vendored sources hold no index, so the registry they replace is queried instead.
+    pub fn resolve(&self, name: &str) -> Result<ResolvedRegistry, TomieError> {
+        let mut current = name.to_string();
+        let mut visited = vec![current.clone()];
+
+        while let Some(replacement) = self
+            .source
+            .get(&current)
+            .and_then(|source| source.replace_with.clone())
+        {
+            if visited.contains(&replacement) {
+                return Err(registry_error(name, "cyclic source replacement"));
+            }
+            if let Some(path) = self.source.get(&replacement).and_then(|s| s.local_path()) {
+                log::warn!(
+                    "Source {} is replaced with the vendored crates in {}; querying its own index instead",
+                    current,
+                    path
+                );
+                break;
+            }
+            log::debug!("Source {} is replaced with {}", current, replacement);
+            visited.push(replacement.clone());
+            current = replacement;
+        }
+
+        let index = if current == CRATES_IO {
+            IndexLocation::CratesIo
+        } else if let Some(url) = self.source.get(&current).and_then(|s| s.registry.clone()) {
+            parse_index(&url)
+        } else if let Some(url) = self.registry_index(&current) {
+            parse_index(&url)
+        } else {
+            return Err(registry_error(
+                name,
+                &format!("no index configured for registry {}", current),
+            ));
+        };
+
+        Ok(ResolvedRegistry {
+            token: match index {
+                IndexLocation::CratesIo => None,
+                _ => self.token(&current),
+            },
+            name: current,
+            index,
+        })
+    }
+
+    fn registry_index(&self, name: &str) -> Option<String> {
+        env::var(format!("CARGO_REGISTRIES_{}_INDEX", env_name(name)))
+            .ok()
+            .or_else(|| self.registries.get(name)?.index.clone())
+    }
+
+    /// Token of a `[registries]` entry from the environment, `credentials.toml` or the
+    /// configuration, as cargo reads them.
+    fn token(&self, name: &str) -> Option<String> {
+        env::var(format!("CARGO_REGISTRIES_{}_TOKEN", env_name(name)))
+            .ok()
+            .or_else(|| self.registries.get(name)?.token.clone())
+    }
+}
+
+fn parse_index(url: &str) -> IndexLocation {
+    match url.strip_prefix("sparse+") {
+        Some(url) => IndexLocation::Sparse(url.trim_end_matches('/').to_string()),
+        None if url.trim_end_matches('/') == CRATES_IO_GIT_INDEX => IndexLocation::CratesIo,
+        None => IndexLocation::Git(url.trim_start_matches("registry+").to_string()),
+    }
+}
+
+fn registry_error(name: &str, reason: &str) -> TomieError {
+    TomieError::RegistryConfig {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Registry names in environment variables are upper case with `-` turned into `_`.
+fn env_name(name: &str) -> String {
+    name.to_uppercase().replace('-', "_")
+}
+
+fn config_file(dir: &Path) -> Option<PathBuf> {
+    existing(dir.join("config.toml")).or_else(|| existing(dir.join("config")))
+}
+
+fn existing(path: PathBuf) -> Option<PathBuf> {
+    path.is_file().then_some(path)
+}
+
+pub fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(text: &str) -> CargoConfig {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn crates_io_without_configuration() {
+        let resolved = CargoConfig::default().resolve(CRATES_IO).unwrap();
+        assert_eq!(resolved.name, CRATES_IO);
+        assert_eq!(resolved.index, IndexLocation::CratesIo);
+    }
+
+    #[test]
+    fn registries_and_their_tokens() {
+        let config = config(
+            r#"
+            [registries.company]
+            index = "sparse+https://cargo.example.com/index/"
+            token = "secret"
+
+            [registries.legacy]
+            index = "https://git.example.com/index.git"
+            "#,
+        );
+        let company = config.resolve("company").unwrap();
+        assert_eq!(
+            company.index,
+            IndexLocation::Sparse("https://cargo.example.com/index".to_string())
+        );
+        assert_eq!(company.token.as_deref(), Some("secret"));
+
+        let legacy = config.resolve("legacy").unwrap();
+        assert_eq!(
+            legacy.index,
+            IndexLocation::Git("https://git.example.com/index.git".to_string())
+        );
+        assert!(config.resolve("unknown").is_err());
+    }
+
+    #[test]
+    fn replacement_chain_ends_at_the_last_source() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "mirror"
+
+            [source.mirror]
+            replace-with = "vendor-mirror"
+
+            [source.vendor-mirror]
+            registry = "sparse+https://mirror.example.com/"
+            "#,
+        );
+        let resolved = config.resolve(CRATES_IO).unwrap();
+        assert_eq!(resolved.name, "vendor-mirror");
+        assert_eq!(
+            resolved.index,
+            IndexLocation::Sparse("https://mirror.example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn replacement_back_to_crates_io_sends_no_token() {
+        let config = config(
+            r#"
+            [registry]
+            default = "company"
+
+            [registries.company]
+            index = "https://github.com/rust-lang/crates.io-index"
+            token = "publish-token"
+
+            [source.mirror]
+            replace-with = "crates-io"
+            "#,
+        );
+        assert_eq!(config.default_registry(), "company");
+        let company = config.resolve("company").unwrap();
+        assert_eq!(company.index, IndexLocation::CratesIo);
+        assert_eq!(company.token, None);
+        assert_eq!(
+            config.resolve("mirror").unwrap().index,
+            IndexLocation::CratesIo
+        );
+    }
+
+    #[test]
+    fn vendored_sources_fall_back_to_the_replaced_registry() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "vendored-sources"
+
+            [source.vendored-sources]
+            directory = "vendor"
+
+            [source.company]
+            replace-with = "local"
+
+            [source.local]
+            local-registry = "/srv/registry"
+
+            [registries.company]
+            index = "sparse+https://cargo.example.com/index/"
+            "#,
+        );
+        let resolved = config.resolve(CRATES_IO).unwrap();
+        assert_eq!(resolved.name, CRATES_IO);
+        assert_eq!(resolved.index, IndexLocation::CratesIo);
+
+        let company = config.resolve("company").unwrap();
+        assert_eq!(company.name, "company");
+        assert_eq!(
+            company.index,
+            IndexLocation::Sparse("https://cargo.example.com/index".to_string())
+        );
+    }
+
+    #[test]
+    fn replacement_cycles_are_errors() {
+        let config = config(
+            r#"
+            [source.crates-io]
+            replace-with = "a"
+
+            [source.a]
+            replace-with = "b"
+
+            [source.b]
+            replace-with = "a"
+            "#,
+        );
+        let error = config.resolve(CRATES_IO).unwrap_err();
+        assert_eq!(error.kind(), "registry-config");
+        assert!(error.to_string().contains("cyclic source replacement"));
+    }
+
+    #[test]
+    fn closer_files_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join(".cargo")).unwrap();
+        fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        fs::write(
+            dir.path().join(".cargo/config.toml"),
+            "[registries.company]\nindex = \"sparse+https://outer.example.com/\"\ntoken = \"outer\"\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join(".cargo/config.toml"),
+            "[registries.company]\nindex = \"sparse+https://inner.example.com/\"\n",
+        )
+        .unwrap();
+
+        let config = CargoConfig::load(&project).unwrap();
+        let company = config.resolve("company").unwrap();
+        assert_eq!(
+            company.index,
+            IndexLocation::Sparse("https://inner.example.com".to_string())
+        );
+        assert_eq!(company.token.as_deref(), Some("outer"));
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum TomieError {
+    ManifestIo {
+        path: PathBuf,
+        source: io::Error,
+    },
+    TomlParse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    TomlEdit {
+        path: PathBuf,
+        source: toml_edit::TomlError,
+    },
+    MemberPattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+    HttpStatus {
+        name: String,
+        status: reqwest::StatusCode,
+    },
+    HttpClient {
+        source: reqwest::Error,
+    },
+    Network {
+        name: String,
+        source: reqwest::Error,
+    },
+    JsonShape {
+        name: String,
+        reason: String,
+    },
+    VersionParse {
+        name: String,
+        version: String,
+        source: semver::Error,
+    },
+    CrateNotFound {
+        name: String,
+    },
+    NoMatchingVersion {
+        name: String,
+        requirement: String,
+    },
+    NoLocalIndex {
+        path: PathBuf,
+    },
+    RegistryConfig {
+        name: String,
+        reason: String,
+    },
+    Git {
+        url: String,
+        reason: String,
+    },
+}
+
+impl TomieError {
+    /// Stable identifier of the failure, used by the JSON report.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TomieError::ManifestIo { .. } => "manifest-io",
+            TomieError::TomlParse { .. } => "toml-parse",
+            TomieError::TomlEdit { .. } => "toml-edit",
+            TomieError::MemberPattern { .. } => "member-pattern",
+            TomieError::HttpStatus { .. } => "http-status",
+            TomieError::HttpClient { .. } => "http-client",
+            TomieError::Network { .. } => "network",
+            TomieError::JsonShape { .. } => "json-shape",
+            TomieError::VersionParse { .. } => "version-parse",
+            TomieError::CrateNotFound { .. } => "crate-not-found",
+            TomieError::NoMatchingVersion { .. } => "no-matching-version",
+            TomieError::NoLocalIndex { .. } => "no-local-index",
+            TomieError::RegistryConfig { .. } => "registry-config",
+            TomieError::Git { .. } => "git",
+        }
+    }
+}
+
+impl fmt::Display for TomieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TomieError::ManifestIo { path, source } => {
+                write!(f, "Unable to read {}: {}", path.display(), source)
+            }
+            TomieError::TomlParse { path, source } => {
+                write!(f, "Unable to parse {}: {}", path.display(), source)
+            }
+            TomieError::TomlEdit { path, source } => {
+                write!(f, "Unable to edit {}: {}", path.display(), source)
+            }
+            TomieError::MemberPattern { pattern, source } => {
+                write!(
+                    f,
+                    "Invalid workspace member pattern {}: {}",
+                    pattern, source
+                )
+            }
+            TomieError::HttpStatus { name, status } => write!(
+                f,
+                "HTTP error {} for {}: {}",
+                status.as_u16(),
+                name,
+                status.canonical_reason().unwrap_or("Unknown error")
+            ),
+            TomieError::HttpClient { source } => {
+                write!(f, "Unable to set up the HTTP client: {}", source)
+            }
+            TomieError::Network { name, source } => {
+                write!(f, "Request for {} failed: {}", name, source)
+            }
+            TomieError::JsonShape { name, reason } => {
+                write!(f, "Unexpected registry response for {}: {}", name, reason)
+            }
+            TomieError::VersionParse {
+                name,
+                version,
+                source,
+            } => write!(f, "Invalid version {} for {}: {}", version, name, source),
+            TomieError::CrateNotFound { name } => {
+                write!(f, "Crate {} not found on the registry", name)
+            }
+            TomieError::NoMatchingVersion { name, requirement } => write!(
+                f,
+                "No release of {} on the registry is as new as {}",
+                name, requirement
+            ),
+            TomieError::RegistryConfig { name, reason } => {
+                write!(f, "Unable to use registry {}: {}", name, reason)
+            }
+            TomieError::Git { url, reason } => write!(f, "Git repository {}: {}", url, reason),
+            TomieError::NoLocalIndex { path } => write!(
+                f,
+                "No local registry index found in {}; run cargo once with network access",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TomieError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TomieError::ManifestIo { source, .. } => Some(source),
+            TomieError::TomlParse { source, .. } => Some(source),
+            TomieError::TomlEdit { source, .. } => Some(source),
+            TomieError::MemberPattern { source, .. } => Some(source),
+            TomieError::HttpClient { source } => Some(source),
+            TomieError::Network { source, .. } => Some(source),
+            TomieError::VersionParse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
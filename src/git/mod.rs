@@ -0,0 +1,432 @@
+use crate::error::TomieError;
+use crate::utils::{GitReference, GitSpec};
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where a git dependency stands against its remote.
+#[derive(Debug, Clone)]
+pub struct GitStatus {
+    /// Commit the manifest points at.
+    pub pinned_commit: String,
+    /// What the pinned commit is compared with, e.g. `tag v1.2.0` or `branch main`.
+    pub target: String,
+    pub target_commit: String,
+    /// Commits of the target missing from the pinned commit; `None` when the
+    /// dependency follows a branch and `Cargo.lock` pins no commit of it.
+    pub commits_behind: Option<usize>,
+}
+
+/// A bare copy of a remote repository, kept up to date with `git fetch`.
+#[derive(Debug)]
+pub struct GitMirror {
+    dir: PathBuf,
+    url: String,
+}
+
+impl GitMirror {
+    /// Opens the mirror of `url` under `root`, fetching the remote's branches and
+    /// tags first unless `offline`.
+    pub fn open(root: &Path, url: &str, offline: bool) -> Result<Self, TomieError> {
+        let mirror = GitMirror {
+            dir: root.join(mirror_name(url)),
+            url: url.to_string(),
+        };
+
+        if offline {
+            if !mirror.dir.join("HEAD").is_file() {
+                return Err(mirror.error(format!(
+                    "no local copy in {}; run once without --offline",
+                    mirror.dir.display()
+                )));
+            }
+            return Ok(mirror);
+        }
+
+        let created = !mirror.dir.join("HEAD").is_file();
+        if created {
+            fs::create_dir_all(&mirror.dir).map_err(|source| TomieError::ManifestIo {
+                path: mirror.dir.clone(),
+                source,
+            })?;
+            mirror.git(&["init", "--bare", "--quiet"])?;
+        }
+        log::info!("Fetching {} into {}", url, mirror.dir.display());
+        let fetched = mirror.git(&[
+            "fetch",
+            "--quiet",
+            "--prune",
+            "--force",
+            // URLs come from manifests and crate metadata; never take one for an option.
+            "--",
+            url,
+            "+refs/heads/*:refs/heads/*",
+            "+refs/tags/*:refs/tags/*",
+        ]);
+        if let Err(e) = fetched {
+            // An empty mirror would later pass for an offline copy.
+            if created {
+                let _ = fs::remove_dir_all(&mirror.dir);
+            }
+            return Err(e);
+        }
+
+        // The remote's default branch is what a dependency without branch, tag or rev follows.
+        let symref = mirror.git(&["ls-remote", "--symref", "--", url, "HEAD"])?;
+        if let Some(branch) = symref
+            .lines()
+            .find_map(|line| line.strip_prefix("ref: ")?.split_whitespace().next())
+        {
+            mirror.git(&["symbolic-ref", "HEAD", branch])?;
+        }
+        Ok(mirror)
+    }
+
+    fn error(&self, reason: String) -> TomieError {
+        TomieError::Git {
+            url: self.url.clone(),
+            reason,
+        }
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, TomieError> {
+        log::trace!("git {} in {}", args.join(" "), self.dir.display());
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| self.error(format!("unable to run git: {}", e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let message = stderr.lines().next().unwrap_or_default();
+            return Err(self.error(format!("git {} failed: {}", args[0], message)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Full hash of the commit `rev` names.
+    pub fn resolve(&self, rev: &str) -> Result<String, TomieError> {
+        self.git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", rev),
+        ])
+        .map_err(|_| self.error(format!("{} not found in the repository", rev)))
+    }
+
+    /// Name of the branch the remote's HEAD points at.
+    fn default_branch(&self) -> Result<String, TomieError> {
+        self.git(&["symbolic-ref", "--short", "HEAD"])
+    }
+
+    /// The highest tag by semver (ignoring a `v` prefix), else the most recently created one.
+    pub fn newest_tag(&self) -> Result<Option<String>, TomieError> {
+        let tags = self.git(&[
+            "for-each-ref",
+            "--sort=-creatordate",
+            "--format=%(refname:short)",
+            "refs/tags",
+        ])?;
+        let tags: Vec<&str> = tags.lines().collect();
+        let by_semver = tags
+            .iter()
+            .filter_map(|tag| Some((Version::parse(tag.trim_start_matches('v')).ok()?, *tag)))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, tag)| tag);
+        Ok(by_semver.or(tags.first().copied()).map(String::from))
+    }
+
+    /// Paths of every file at the head of the default branch.
+    pub fn files(&self) -> Result<Vec<String>, TomieError> {
+        let files = self.git(&["ls-tree", "-r", "--name-only", "HEAD"])?;
+        Ok(files.lines().map(String::from).collect())
+    }
+
+    /// Content of `path` at the head of the default branch.
+    pub fn read_file(&self, path: &str) -> Result<String, TomieError> {
+        self.git(&["show", &format!("HEAD:{}", path)])
+    }
+
+    /// Number of commits reachable from `to` but not from `from`.
+    pub fn commits_between(&self, from: &str, to: &str) -> Result<usize, TomieError> {
+        let count = self.git(&["rev-list", "--count", &format!("{}..{}", from, to)])?;
+        count
+            .parse()
+            .map_err(|_| self.error(format!("unexpected rev-list output {:?}", count)))
+    }
+
+    /// Compares what `spec` points at with the newest tag (tag pins) or the head of
+    /// the branch it would otherwise follow; a branch dependency is pinned at `locked`,
+    /// the commit `Cargo.lock` recorded, when there is one.
+    pub fn status(&self, spec: &GitSpec, locked: Option<&str>) -> Result<GitStatus, TomieError> {
+        match &spec.reference {
+            GitReference::Tag(tag) => {
+                let pinned_commit = self.resolve(&format!("refs/tags/{}", tag))?;
+                let newest = self.newest_tag()?.unwrap_or_else(|| tag.clone());
+                let target_commit = self.resolve(&format!("refs/tags/{}", newest))?;
+                Ok(GitStatus {
+                    commits_behind: Some(self.commits_between(&pinned_commit, &target_commit)?),
+                    pinned_commit,
+                    target: format!("tag {}", newest),
+                    target_commit,
+                })
+            }
+            GitReference::Rev(rev) => {
+                let pinned_commit = self.resolve(rev)?;
+                let branch = match &spec.branch {
+                    Some(branch) => branch.clone(),
+                    None => self.default_branch()?,
+                };
+                let target_commit = self.resolve(&format!("refs/heads/{}", branch))?;
+                Ok(GitStatus {
+                    commits_behind: Some(self.commits_between(&pinned_commit, &target_commit)?),
+                    pinned_commit,
+                    target: format!("branch {}", branch),
+                    target_commit,
+                })
+            }
+            GitReference::Branch(_) | GitReference::DefaultBranch => {
+                let branch = match &spec.reference {
+                    GitReference::Branch(branch) => branch.clone(),
+                    _ => self.default_branch()?,
+                };
+                let head = self.resolve(&format!("refs/heads/{}", branch))?;
+                let Some(locked) = locked else {
+                    return Ok(GitStatus {
+                        pinned_commit: head.clone(),
+                        target: format!("branch {}", branch),
+                        target_commit: head,
+                        commits_behind: None,
+                    });
+                };
+                let pinned_commit = self.resolve(locked)?;
+                Ok(GitStatus {
+                    commits_behind: Some(self.commits_between(&pinned_commit, &head)?),
+                    pinned_commit,
+                    target: format!("branch {}", branch),
+                    target_commit: head,
+                })
+            }
+        }
+    }
+}
+
+/// Directory name of the mirror of `url`: readable, with a hash of the whole URL so
+/// that e.g. `http://` and `https://`, or `a/b_c` and `a/b/c`, never share a mirror.
+fn mirror_name(url: &str) -> String {
+    let readable: String = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("ssh://")
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{:016x}", readable, fnv1a(url.as_bytes()))
+}
+
+/// 64-bit FNV-1a; unlike `DefaultHasher`, stable across Rust releases, so mirrors
+/// written by one build are found by the next.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs git in `dir`, with an identity for commits and tags.
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn commit(dir: &Path, message: &str) -> String {
+        git(dir, &["commit", "--quiet", "--allow-empty", "-m", message]);
+        git(dir, &["rev-parse", "HEAD"])
+    }
+
+    fn spec(url: &str, reference: GitReference, branch: Option<&str>) -> GitSpec {
+        GitSpec {
+            url: url.to_string(),
+            reference,
+            branch: branch.map(String::from),
+        }
+    }
+
+    /// A bare repository whose `main` has four commits, tagged `v0.1.0`, -, `v0.10.0`
+    /// and `v0.9.0` (tagged last), and whose `release` branch adds two to the first.
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        url: String,
+        mirrors: PathBuf,
+        commits: Vec<String>,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        fs::create_dir(&work).unwrap();
+        git(&work, &["init", "--quiet", "--initial-branch=main"]);
+        fs::write(work.join("CHANGELOG.md"), "# 0.1.0\n").unwrap();
+        git(&work, &["add", "CHANGELOG.md"]);
+        let commits: Vec<String> = (1..=4)
+            .map(|i| commit(&work, &format!("commit {}", i)))
+            .collect();
+        git(&work, &["tag", "v0.1.0", &commits[0]]);
+        git(&work, &["tag", "v0.10.0", &commits[2]]);
+        git(&work, &["tag", "v0.9.0", &commits[3]]);
+        git(
+            &work,
+            &["checkout", "--quiet", "-b", "release", &commits[0]],
+        );
+        commit(&work, "release 1");
+        commit(&work, "release 2");
+        git(&work, &["checkout", "--quiet", "main"]);
+
+        let bare = dir.path().join("dep.git");
+        git(
+            dir.path(),
+            &["clone", "--quiet", "--bare", "work", "dep.git"],
+        );
+        Fixture {
+            url: bare.to_string_lossy().into_owned(),
+            mirrors: dir.path().join("mirrors"),
+            _dir: dir,
+            commits,
+        }
+    }
+
+    #[test]
+    fn tag_pins_compare_with_the_highest_tag() {
+        let fixture = fixture();
+        let mirror = GitMirror::open(&fixture.mirrors, &fixture.url, false).unwrap();
+        assert_eq!(mirror.newest_tag().unwrap().as_deref(), Some("v0.10.0"));
+
+        let tag = spec(&fixture.url, GitReference::Tag("v0.1.0".into()), None);
+        let status = mirror.status(&tag, None).unwrap();
+        assert_eq!(status.pinned_commit, fixture.commits[0]);
+        assert_eq!(status.target, "tag v0.10.0");
+        assert_eq!(status.target_commit, fixture.commits[2]);
+        assert_eq!(status.commits_behind, Some(2));
+    }
+
+    #[test]
+    fn rev_pins_compare_with_their_branch() {
+        let fixture = fixture();
+        let mirror = GitMirror::open(&fixture.mirrors, &fixture.url, false).unwrap();
+        let first = &fixture.commits[0];
+
+        let rev = spec(&fixture.url, GitReference::Rev(first[..8].into()), None);
+        let status = mirror.status(&rev, None).unwrap();
+        assert_eq!(status.pinned_commit, *first);
+        assert_eq!(status.target, "branch main");
+        assert_eq!(status.commits_behind, Some(3));
+
+        let rev = spec(
+            &fixture.url,
+            GitReference::Rev(first.clone()),
+            Some("release"),
+        );
+        let status = mirror.status(&rev, None).unwrap();
+        assert_eq!(status.target, "branch release");
+        assert_eq!(status.commits_behind, Some(2));
+
+        let missing = spec(&fixture.url, GitReference::Rev("deadbeef".into()), None);
+        assert!(mirror.status(&missing, None).is_err());
+    }
+
+    #[test]
+    fn branches_are_behind_only_from_a_locked_commit() {
+        let fixture = fixture();
+        let mirror = GitMirror::open(&fixture.mirrors, &fixture.url, false).unwrap();
+
+        let release = spec(&fixture.url, GitReference::Branch("release".into()), None);
+        let status = mirror.status(&release, None).unwrap();
+        assert_eq!(status.target, "branch release");
+        assert_eq!(status.pinned_commit, status.target_commit);
+        assert_eq!(status.commits_behind, None);
+
+        let default = spec(&fixture.url, GitReference::DefaultBranch, None);
+        let status = mirror.status(&default, Some(&fixture.commits[1])).unwrap();
+        assert_eq!(status.target, "branch main");
+        assert_eq!(status.pinned_commit, fixture.commits[1]);
+        assert_eq!(status.target_commit, fixture.commits[3]);
+        assert_eq!(status.commits_behind, Some(2));
+    }
+
+    #[test]
+    fn offline_needs_an_earlier_fetch() {
+        let fixture = fixture();
+        let error = GitMirror::open(&fixture.mirrors, &fixture.url, true).unwrap_err();
+        assert_eq!(error.kind(), "git");
+
+        GitMirror::open(&fixture.mirrors, &fixture.url, false).unwrap();
+        let mirror = GitMirror::open(&fixture.mirrors, &fixture.url, true).unwrap();
+        assert_eq!(mirror.files().unwrap(), ["CHANGELOG.md"]);
+        assert_eq!(mirror.read_file("CHANGELOG.md").unwrap(), "# 0.1.0");
+    }
+
+    #[test]
+    fn failed_first_fetch_leaves_no_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = dir
+            .path()
+            .join("missing.git")
+            .to_string_lossy()
+            .into_owned();
+        assert!(GitMirror::open(dir.path(), &url, false).is_err());
+        assert!(!dir.path().join(mirror_name(&url)).exists());
+    }
+
+    #[test]
+    fn urls_are_never_options() {
+        let root = tempfile::tempdir().unwrap();
+        let marker = root.path().join("marker");
+        let url = format!("--upload-pack=touch {}", marker.display());
+        let error = GitMirror::open(root.path(), &url, false).unwrap_err();
+        assert_eq!(error.kind(), "git");
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn mirror_names_are_readable_and_unique() {
+        assert!(
+            mirror_name("https://github.com/owner/repo.git").starts_with("github.com_owner_repo-")
+        );
+        assert!(mirror_name("ssh://git@host/x/").starts_with("git_host_x-"));
+        assert_eq!(
+            mirror_name("https://github.com/owner/repo"),
+            mirror_name("https://github.com/owner/repo")
+        );
+        assert_ne!(
+            mirror_name("https://github.com/owner/repo"),
+            mirror_name("http://github.com/owner/repo")
+        );
+        assert_ne!(
+            mirror_name("https://host/a/b_c"),
+            mirror_name("https://host/a/b/c")
+        );
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::update::{is_rewritable, ProposedUpdate};
+use crate::utils::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::ops::ControlFlow;
+
+const HELP: &str = "↑/↓ move  space toggle  ←/→ target version  a toggle all  enter apply  q quit";
+
+/// One outdated dependency and what the user picked for it.
+struct Choice<'a> {
+    analysis: &'a DependencyAnalysis,
+    selected: bool,
+    /// Index of the target version in `analysis.candidates`.
+    target: usize,
+}
+
+impl Choice<'_> {
+    fn target(&self) -> &str {
+        &self.analysis.candidates[self.target]
+    }
+
+    fn update(&self) -> Option<ProposedUpdate> {
+        ProposedUpdate::new(self.analysis, self.target())
+    }
+}
+
+struct Picker<'a> {
+    choices: Vec<Choice<'a>>,
+    state: TableState,
+}
+
+impl<'a> Picker<'a> {
+    /// Every outdated dependency whose requirement can be rewritten, compatible
+    /// updates selected and the latest release targeted. An entry of
+    /// `[workspace.dependencies]` is one row, however many members inherit it.
+    fn new(analyses: &'a [DependencyAnalysis]) -> Self {
+        let mut inherited = BTreeSet::new();
+        let choices: Vec<Choice> = analyses
+            .iter()
+            .filter(|a| a.is_outdated() && !a.candidates.is_empty())
+            .filter(|a| is_rewritable(&a.current_version))
+            .filter(|a| !a.from_workspace || inherited.insert(a.name.as_str()))
+            .map(|analysis| Choice {
+                analysis,
+                selected: analysis.status == UpdateStatus::CompatibleUpdate,
+                target: analysis.candidates.len() - 1,
+            })
+            .collect();
+        let mut state = TableState::default();
+        state.select((!choices.is_empty()).then_some(0));
+        Picker { choices, state }
+    }
+
+    fn current(&mut self) -> Option<&mut Choice<'a>> {
+        let index = self.state.selected()?;
+        self.choices.get_mut(index)
+    }
+
+    /// Handles keys until the selection is applied (`Some`) or abandoned (`None`).
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Option<Vec<ProposedUpdate>>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let ControlFlow::Break(result) = self.handle_key(key.code) {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Applies one key press; breaks with the selected updates on enter, or with
+    /// `None` when the user quits.
+    fn handle_key(&mut self, code: KeyCode) -> ControlFlow<Option<Vec<ProposedUpdate>>> {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return ControlFlow::Break(None),
+            KeyCode::Enter => {
+                let updates = self
+                    .choices
+                    .iter()
+                    .filter(|c| c.selected)
+                    .filter_map(Choice::update)
+                    .collect();
+                return ControlFlow::Break(Some(updates));
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+            KeyCode::Char(' ') => {
+                if let Some(choice) = self.current() {
+                    choice.selected = !choice.selected;
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                if let Some(choice) = self.current() {
+                    choice.target = choice.target.saturating_sub(1);
+                    choice.selected = true;
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(choice) = self.current() {
+                    choice.target = (choice.target + 1).min(choice.analysis.candidates.len() - 1);
+                    choice.selected = true;
+                }
+            }
+            KeyCode::Char('a') => {
+                let select = !self.choices.iter().all(|c| c.selected);
+                for choice in &mut self.choices {
+                    choice.selected = select;
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, help_area] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+        let header = Row::new([
+            "",
+            "Crate",
+            "Member",
+            "Section",
+            "Current",
+            "Compatible",
+            "Latest",
+            "Target",
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.choices.iter().map(|choice| {
+            let analysis = choice.analysis;
+            let last = analysis.candidates.len() - 1;
+            let target = format!(
+                "{} {} {}",
+                if choice.target > 0 { "<" } else { " " },
+                choice.target(),
+                if choice.target < last { ">" } else { " " }
+            );
+            let (member, section) = if analysis.from_workspace {
+                (
+                    "workspace".to_string(),
+                    "[workspace.dependencies]".to_string(),
+                )
+            } else {
+                (analysis.member.clone(), analysis.section.to_string())
+            };
+            Row::new([
+                if choice.selected { "[x]" } else { "[ ]" }.to_string(),
+                analysis.name.clone(),
+                member,
+                section,
+                analysis.current_version.clone(),
+                analysis.compatible_version.clone().unwrap_or_default(),
+                analysis.latest_version.clone(),
+                target,
+            ])
+        });
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(16),
+        ];
+        let selected = self.choices.iter().filter(|c| c.selected).count();
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::new().borders(Borders::ALL).title(format!(
+                " Outdated dependencies ({} of {} selected) ",
+                selected,
+                self.choices.len()
+            )))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(table, table_area, &mut self.state);
+        frame.render_widget(Line::from(HELP), help_area);
+    }
+}
+
+/// Lets the user pick, in the terminal, which outdated dependencies to update and to
+/// which version; `None` when they quit without applying.
+pub fn pick_updates(
+    analyses: &[DependencyAnalysis],
+) -> Result<Option<Vec<ProposedUpdate>>, Box<dyn Error>> {
+    let mut picker = Picker::new(analyses);
+    if picker.choices.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err("interactive mode needs a terminal".into());
+    }
+
+    let mut terminal = ratatui::init();
+    let result = picker.run(&mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn analysis(
+        name: &str,
+        member: &str,
+        from_workspace: bool,
+        status: UpdateStatus,
+        candidates: &[&str],
+    ) -> DependencyAnalysis {
+        DependencyAnalysis {
+            name: name.to_string(),
+            package: None,
+            member: member.to_string(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", member)),
+            from_workspace,
+            section: DependencySection {
+                kind: DependencyKind::Normal,
+                target: None,
+            },
+            current_version: "0.1.0".to_string(),
+            registry: None,
+            locked_version: None,
+            compatible_version: Some("0.1.5".to_string()),
+            latest_version: candidates.last().unwrap_or(&"0.1.5").to_string(),
+            status,
+            action: UpdateAction::EditManifest,
+            pinned_yanked: false,
+            latest_rust_version: None,
+            candidates: candidates.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn targets(updates: &[ProposedUpdate]) -> Vec<(&str, &str)> {
+        updates
+            .iter()
+            .map(|u| (u.name.as_str(), u.to.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn one_row_per_outdated_requirement() {
+        let analyses = [
+            analysis(
+                "serde",
+                "app",
+                true,
+                UpdateStatus::BreakingUpdate,
+                &["0.1.5", "1.0.0"],
+            ),
+            analysis(
+                "serde",
+                "lib",
+                true,
+                UpdateStatus::BreakingUpdate,
+                &["0.1.5", "1.0.0"],
+            ),
+            analysis(
+                "log",
+                "lib",
+                false,
+                UpdateStatus::CompatibleUpdate,
+                &["0.1.5"],
+            ),
+            analysis("rand", "lib", false, UpdateStatus::UpToDate, &["0.1.5"]),
+        ];
+        let picker = Picker::new(&analyses);
+        let rows: Vec<(&str, &str, bool, usize)> = picker
+            .choices
+            .iter()
+            .map(|c| {
+                (
+                    c.analysis.name.as_str(),
+                    c.analysis.member.as_str(),
+                    c.selected,
+                    c.target,
+                )
+            })
+            .collect();
+        assert_eq!(rows, [("serde", "app", false, 1), ("log", "lib", true, 0)]);
+    }
+
+    #[test]
+    fn keys_pick_updates_and_targets() {
+        let analyses = [
+            analysis(
+                "log",
+                "app",
+                false,
+                UpdateStatus::CompatibleUpdate,
+                &["0.1.5"],
+            ),
+            analysis(
+                "serde",
+                "app",
+                false,
+                UpdateStatus::BreakingUpdate,
+                &["0.1.5", "0.2.3", "1.0.0"],
+            ),
+        ];
+        let mut picker = Picker::new(&analyses);
+
+        assert!(picker.handle_key(KeyCode::Char(' ')).is_continue());
+        assert!(picker.handle_key(KeyCode::Down).is_continue());
+        assert!(picker.handle_key(KeyCode::Left).is_continue());
+        assert!(picker.handle_key(KeyCode::Char('x')).is_continue());
+        let ControlFlow::Break(Some(updates)) = picker.handle_key(KeyCode::Enter) else {
+            panic!("enter applies the selection");
+        };
+        assert_eq!(targets(&updates), [("serde", "0.2.3")]);
+
+        // Toggling all selects everything once something is left out.
+        assert!(picker.handle_key(KeyCode::Char('a')).is_continue());
+        assert!(picker.handle_key(KeyCode::Right).is_continue());
+        assert!(picker.handle_key(KeyCode::Right).is_continue());
+        let ControlFlow::Break(Some(updates)) = picker.handle_key(KeyCode::Enter) else {
+            panic!("enter applies the selection");
+        };
+        assert_eq!(targets(&updates), [("log", "0.1.5"), ("serde", "1.0.0")]);
+
+        assert!(matches!(
+            picker.handle_key(KeyCode::Char('q')),
+            ControlFlow::Break(None)
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::error::TomieError;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct LockfileToml {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    /// Absent for path dependencies and workspace members.
+    source: Option<String>,
+}
+
+/// Versions `Cargo.lock` resolved registry crates to; a crate may be locked at several.
+#[derive(Debug, Default)]
+pub struct Lockfile {
+    packages: HashMap<String, Vec<Version>>,
+    /// Repository and commit of every git crate, by crate name.
+    git: HashMap<String, Vec<(String, String)>>,
+}
+
+/// A repository URL without the trailing `/` or `.git` that may or may not be written.
+fn same_repository(url: &str) -> &str {
+    url.trim_end_matches('/').trim_end_matches(".git")
+}
+
+impl Lockfile {
+    /// Reads `Cargo.lock` in `dir`, if there is one.
+    pub fn load(dir: &Path) -> Result<Option<Self>, TomieError> {
+        let path = dir.join("Cargo.lock");
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(TomieError::ManifestIo { path, source }),
+        };
+        let lockfile: LockfileToml =
+            toml::from_str(&content).map_err(|source| TomieError::TomlParse { path, source })?;
+
+        let mut packages: HashMap<String, Vec<Version>> = HashMap::new();
+        let mut git: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for package in lockfile.package {
+            // `git+https://host/repo?branch=main#<commit>`
+            if let Some((url, commit)) = package
+                .source
+                .as_deref()
+                .and_then(|s| s.strip_prefix("git+"))
+                .and_then(|s| s.split_once('#'))
+            {
+                let url = url.split_once('?').map_or(url, |(url, _)| url);
+                git.entry(package.name)
+                    .or_default()
+                    .push((url.to_string(), commit.to_string()));
+                continue;
+            }
+            let from_registry = package
+                .source
+                .as_deref()
+                .is_some_and(|s| s.starts_with("registry+") || s.starts_with("sparse+"));
+            if !from_registry {
+                continue;
+            }
+            match Version::parse(&package.version) {
+                Ok(version) => packages.entry(package.name).or_default().push(version),
+                Err(e) => log::debug!(
+                    "Ignoring locked {} {}: {}",
+                    package.name,
+                    package.version,
+                    e
+                ),
+            }
+        }
+        Ok(Some(Lockfile { packages, git }))
+    }
+
+    /// The version of `name` locked for a dependency requiring `req`.
+    pub fn locked_version(&self, name: &str, req: &VersionReq) -> Option<&Version> {
+        self.packages
+            .get(name)?
+            .iter()
+            .filter(|v| req.matches(v))
+            .max()
+    }
+
+    /// The commit of `name` locked from the repository at `url`.
+    pub fn locked_commit(&self, name: &str, url: &str) -> Option<&str> {
+        self.git
+            .get(name)?
+            .iter()
+            .find(|(locked, _)| same_repository(locked) == same_repository(url))
+            .map(|(_, commit)| commit.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "private"
+version = "1.4.0"
+source = "sparse+https://cargo.example.com/index/"
+
+[[package]]
+name = "forked"
+version = "0.2.0"
+source = "git+https://github.com/owner/forked?branch=main#0123456789abcdef"
+
+[[package]]
+name = "weird"
+version = "not-a-version"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn load(content: &str) -> Option<Lockfile> {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.lock"), content).unwrap();
+        Lockfile::load(dir.path()).unwrap()
+    }
+
+    fn req(text: &str) -> VersionReq {
+        VersionReq::parse(text).unwrap()
+    }
+
+    #[test]
+    fn locked_version_matching_the_requirement() {
+        let lockfile = load(LOCKFILE).unwrap();
+        assert_eq!(
+            lockfile.locked_version("rand", &req("0.7")),
+            Some(&Version::new(0, 7, 3))
+        );
+        assert_eq!(
+            lockfile.locked_version("rand", &req("0.8")),
+            Some(&Version::new(0, 8, 5))
+        );
+        assert_eq!(
+            lockfile.locked_version("rand", &req(">=0.7")),
+            Some(&Version::new(0, 8, 5))
+        );
+        assert_eq!(lockfile.locked_version("rand", &req("0.9")), None);
+        assert_eq!(
+            lockfile.locked_version("private", &req("1")),
+            Some(&Version::new(1, 4, 0))
+        );
+    }
+
+    #[test]
+    fn only_registry_crates_have_locked_versions() {
+        let lockfile = load(LOCKFILE).unwrap();
+        assert_eq!(lockfile.locked_version("app", &req("*")), None);
+        assert_eq!(lockfile.locked_version("forked", &req("*")), None);
+        assert_eq!(lockfile.locked_version("weird", &req("*")), None);
+    }
+
+    #[test]
+    fn locked_commit_of_git_crates() {
+        let lockfile = load(LOCKFILE).unwrap();
+        assert_eq!(
+            lockfile.locked_commit("forked", "https://github.com/owner/forked.git"),
+            Some("0123456789abcdef")
+        );
+        assert_eq!(
+            lockfile.locked_commit("forked", "https://github.com/other/forked"),
+            None
+        );
+        assert_eq!(
+            lockfile.locked_commit("rand", "https://github.com/rust-random/rand"),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Lockfile::load(dir.path()).unwrap().is_none());
+    }
+}
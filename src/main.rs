@@ -1,183 +1,947 @@
-use futures::future::join_all;
-use reqwest;
-use semver::Version;
-use std::env;
+use futures::stream::{self, StreamExt};
+use semver::{Version, VersionReq};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error;
-use std::fs;
-use std::path::PathBuf;
-use tokio;
-use toml;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+mod changelog;
+mod cli;
+mod config;
+mod error;
+mod git;
+mod interactive;
+mod lockfile;
+mod registry;
+mod report;
+mod update;
 mod utils;
+mod workspace;
+use crate::changelog::{find_repository, load_changelog, Changelog};
+use crate::cli::{Cli, Command, GlobalArgs};
+use crate::config::{CargoConfig, CRATES_IO};
+use crate::error::TomieError;
+use crate::git::GitMirror;
+use crate::interactive::pick_updates;
+use crate::lockfile::Lockfile;
+use crate::registry::{
+    Cache, CacheMode, HttpClient, HttpOptions, MemorySource, Protocol, Registries, RetryPolicy,
+    CRATES_IO_API,
+};
+use crate::report::{print_explain, print_json_report, print_text_report};
+use crate::update::{edit_manifests, print_diffs, print_review, print_updates, propose_updates};
 use crate::utils::*;
+use crate::workspace::{load_members, path_mismatches, Member};
 
-type AnalysisResult = Result<Option<DependencyAnalysis>, Box<dyn Error>>;
+type AnalysisResult = Result<DependencyAnalysis, TomieError>;
 
-async fn normalize_version(version: &str) -> String {
-    let parts: Vec<&str> = version.trim_start_matches('^').split('.').collect();
+/// Parses the published versions, dropping yanked ones and, unless asked for, pre-releases.
+/// Like cargo, pre-releases that `req` matches, e.g. 2.0.0-rc.2 for `2.0.0-rc.1`, are kept.
+fn parse_versions(
+    name: &str,
+    versions: &[CrateVersion],
+    req: Option<&VersionReq>,
+    options: &AnalysisOptions,
+) -> Vec<Version> {
+    versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .filter_map(|v| match Version::parse(&v.num) {
+            Ok(version) => Some(version),
+            Err(e) => {
+                log::debug!("Parsing error for version {} of {}: {}", v.num, name, e);
+                None
+            }
+        })
+        .filter(|v| {
+            options.include_prerelease || v.pre.is_empty() || req.is_some_and(|req| req.matches(v))
+        })
+        .collect()
+}
+
+/// The exact version a requirement such as `1.2.3` or `=1.2.3` starts from, if it names one.
+fn pinned_version(req: &VersionReq) -> Option<Version> {
+    let comparator = req.comparators.first()?;
+    Some(Version {
+        major: comparator.major,
+        minor: comparator.minor?,
+        patch: comparator.patch?,
+        pre: comparator.pre.clone(),
+        build: Default::default(),
+    })
+}
 
-    match parts.len() {
-        1 => format!("{}.0.0", parts[0]),
-        2 => format!("{}.{}.0", parts[0], parts[1]),
-        _ => version.trim_start_matches('^').to_string(),
+/// The newest release of every semver-compatible line from `compatible` on, e.g.
+/// 0.12.28, 0.13.5 and 1.2.0 for a `0.12` requirement: the versions worth upgrading to.
+/// Without a compatible release, e.g. for a yanked pin, those above what `req` accepts.
+fn upgrade_candidates(
+    versions: &[Version],
+    req: &VersionReq,
+    compatible: Option<&Version>,
+) -> Vec<Version> {
+    let line = |v: &Version| match (v.major, v.minor) {
+        (0, 0) => (0, 0, v.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    };
+    let floor = requirement_floor(req);
+    let mut newest: BTreeMap<(u64, u64, u64), &Version> = BTreeMap::new();
+    for version in versions.iter().filter(|v| match (compatible, &floor) {
+        (Some(compatible), _) => *v >= compatible,
+        (None, Some(floor)) => *v > floor,
+        (None, None) => true,
+    }) {
+        let entry = newest.entry(line(version)).or_insert(version);
+        if version > *entry {
+            *entry = version;
+        }
     }
+    newest.into_values().cloned().collect()
 }
 
-async fn get_crate_versions(name: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    let url = format!("https://crates.io/api/v1/crates/{}", name);
-    println!("Request to API for {}: {}", name, url);
+/// Classifies the newest release against what the requirement already accepts.
+/// None when nothing is published, or only releases older than the requirement.
+fn classify(
+    req: &VersionReq,
+    versions: &[Version],
+) -> Option<(Option<Version>, Version, UpdateStatus)> {
+    let latest = versions.iter().max()?.clone();
+    if requirement_floor(req).is_some_and(|floor| latest < floor) {
+        return None;
+    }
+    let matching: Vec<&Version> = versions.iter().filter(|v| req.matches(v)).collect();
+    let compatible = matching.iter().max().map(|v| (*v).clone());
+    let oldest_matching = matching.iter().min();
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("User-Agent", "cargo-deps-analyzer")
-        .send()
-        .await?;
+    let status = match (&compatible, oldest_matching) {
+        (Some(compatible), Some(oldest)) if *compatible == latest => {
+            if *oldest < compatible {
+                UpdateStatus::CompatibleUpdate
+            } else {
+                UpdateStatus::UpToDate
+            }
+        }
+        _ => UpdateStatus::BreakingUpdate,
+    };
 
-    if !response.status().is_success() {
-        println!(
-            "HTTP error {} for {}: {}",
-            response.status(),
-            name,
-            response
-                .status()
-                .canonical_reason()
-                .unwrap_or("Unknown error")
-        );
-        return Ok(vec![]);
+    Some((compatible, latest, status))
+}
+
+fn analyze_dependency(
+    request: &DependencyRequest,
+    versions: &[CrateVersion],
+    lockfile: Option<&Lockfile>,
+    options: &AnalysisOptions,
+) -> AnalysisResult {
+    let DependencyRequest {
+        name,
+        package,
+        member,
+        manifest_path,
+        from_workspace,
+        section,
+        current_version,
+        registry,
+    } = request;
+    log::debug!(
+        "Dependency analysis for: {} version {} ({})",
+        name,
+        current_version,
+        member
+    );
+
+    let req = VersionReq::parse(current_version).map_err(|source| TomieError::VersionParse {
+        name: name.clone(),
+        version: current_version.clone(),
+        source,
+    })?;
+
+    // Registry data and the lockfile know the crate by its real name.
+    let crate_name = request.crate_name();
+
+    let pinned_yanked = pinned_version(&req).is_some_and(|pinned| {
+        versions
+            .iter()
+            .any(|v| v.yanked && Version::parse(&v.num).is_ok_and(|v| v == pinned))
+    });
+    if pinned_yanked {
+        log::info!("The version of {} pinned by {} has been yanked", name, req);
+    }
+
+    let parsed = parse_versions(crate_name, versions, Some(&req), options);
+    let Some((compatible, latest, status)) = classify(&req, &parsed) else {
+        if parsed.is_empty() {
+            return Err(TomieError::CrateNotFound {
+                name: crate_name.to_string(),
+            });
+        }
+        return Err(TomieError::NoMatchingVersion {
+            name: crate_name.to_string(),
+            requirement: current_version.clone(),
+        });
+    };
+    if compatible.is_none() {
+        log::warn!("No published version of {} satisfies {}", name, req);
     }
+    log::debug!(
+        "Versions for {} : {} -> compatible {:?}, latest {}",
+        name,
+        req,
+        compatible,
+        latest
+    );
 
-    let json = response.json::<serde_json::Value>().await?;
+    let candidates = upgrade_candidates(&parsed, &req, compatible.as_ref());
+    let locked = lockfile.and_then(|lockfile| lockfile.locked_version(crate_name, &req));
+    let action = UpdateAction::new(status, locked, compatible.as_ref());
 
-    let versions = json["versions"]
-        .as_array()
-        .ok_or_else(|| {
-            println!("No versions found for {}", name);
-            "No versions found"
-        })?
+    let latest_rust_version = versions
         .iter()
-        .filter_map(|v| v["num"].as_str().map(String::from))
-        .collect();
+        .find(|v| Version::parse(&v.num).is_ok_and(|v| v == latest))
+        .and_then(|v| v.rust_version.clone());
 
-    Ok(versions)
+    Ok(DependencyAnalysis {
+        name: name.clone(),
+        package: package.clone(),
+        member: member.clone(),
+        manifest_path: manifest_path.clone(),
+        from_workspace: *from_workspace,
+        section: section.clone(),
+        current_version: current_version.clone(),
+        registry: registry.clone(),
+        locked_version: locked.map(|v| v.to_string()),
+        compatible_version: compatible.map(|v| v.to_string()),
+        latest_version: latest.to_string(),
+        status,
+        action,
+        pinned_yanked,
+        latest_rust_version,
+        candidates: candidates.iter().map(|v| v.to_string()).collect(),
+    })
 }
 
-fn parse_dependency_version(dep: &Dependency) -> Option<String> {
-    match dep {
-        Dependency::Simple(version) => Some(version.clone()),
-        Dependency::Detailed(detail) => detail.version.clone(),
+fn collect_requests(members: &[Member]) -> Vec<DependencyRequest> {
+    let mut requests = Vec::new();
+    for member in members {
+        for (section, dependencies) in &member.sections {
+            log::info!(
+                "Dependencies found in {} {} ({}):",
+                member.name,
+                section,
+                member.manifest_path.display()
+            );
+            for (name, dep) in dependencies.iter() {
+                log::trace!("- {}: {:?}", name, dep);
+            }
+
+            // Path and git dependencies are checked against the local crate and the
+            // remote repository instead.
+            let registry_dependencies = dependencies
+                .iter()
+                .filter(|(_, dep)| dep.path().is_none() && dep.git_spec().is_none());
+            for (name, dep) in registry_dependencies {
+                if let Some(current_version) = dep.version().map(String::from) {
+                    requests.push(DependencyRequest {
+                        name: name.clone(),
+                        package: dep.package().map(String::from),
+                        member: member.name.clone(),
+                        manifest_path: member.manifest_path.clone(),
+                        from_workspace: dep.is_from_workspace(),
+                        section: section.clone(),
+                        current_version,
+                        registry: dep.registry().map(String::from),
+                    });
+                }
+            }
+        }
     }
+    requests
 }
 
-async fn analyze_dependency(name: String, current_version: String) -> AnalysisResult {
-    println!(
-        "Dependency analysis for: {} version {} \n",
-        name, current_version
-    );
-    let versions = get_crate_versions(&name).await?;
+async fn analyze_dependencies(
+    registries: &Registries,
+    requests: Vec<DependencyRequest>,
+    lockfile: Option<&Lockfile>,
+    options: &AnalysisOptions,
+) -> Result<AnalysisReport, TomieError> {
+    // Each crate is looked up once per registry, however many members depend on it.
+    let keys: BTreeSet<(&str, &str)> = requests
+        .iter()
+        .map(|r| (registries.name(r.registry.as_deref()), r.crate_name()))
+        .collect();
+    let lookups: Vec<_> = stream::iter(keys)
+        .map(|(registry, name)| async move {
+            let versions = match registries.get(Some(registry)) {
+                Ok(source) => source.list_versions(name).await,
+                Err(e) => Err(e),
+            };
+            ((registry.to_string(), name.to_string()), versions)
+        })
+        .buffer_unordered(options.jobs.max(1))
+        .collect()
+        .await;
+    let versions: HashMap<(String, String), Result<Vec<CrateVersion>, TomieError>> =
+        lookups.into_iter().collect();
+
+    let mut report = AnalysisReport {
+        lookups: LookupStats {
+            total: versions.len(),
+            retried: registries.retried_lookups(),
+            failed: versions.values().filter(|v| v.is_err()).count(),
+        },
+        local_index_age: registries.data_age().map(|age| age.as_secs()),
+        ..Default::default()
+    };
 
-    if versions.is_empty() {
-        println!("No version found {}", name);
-        return Ok(None);
+    for request in requests {
+        let key = (
+            registries.name(request.registry.as_deref()).to_string(),
+            request.crate_name().to_string(),
+        );
+        let result = match &versions[&key] {
+            Ok(crate_versions) => analyze_dependency(&request, crate_versions, lockfile, options),
+            Err(e) => {
+                report.failures.push(DependencyFailure::new(request, e));
+                continue;
+            }
+        };
+        match result {
+            Ok(analysis) => report.dependencies.push(analysis),
+            Err(e) => report.failures.push(DependencyFailure::new(request, &e)),
+        }
     }
 
-    let latest = versions.first().unwrap();
-    let normalized_current = normalize_version(&current_version).await;
-    let normalized_latest = normalize_version(latest).await;
-    println!(
-        "Normalized version for {} : {} -> {}",
-        name, normalized_current, normalized_latest
-    );
+    for failure in &report.failures {
+        log::warn!("{}: {}", failure.name, failure.reason);
+    }
 
-    match (
-        Version::parse(&normalized_current),
-        Version::parse(&normalized_latest),
-    ) {
-        (Ok(current), Ok(latest)) => Ok(Some(DependencyAnalysis {
-            name,
-            current_version: current_version.trim_start_matches('^').to_string(),
-            latest_version: latest.to_string(),
-            is_outdated: latest > current,
-        })),
-        (Err(e), _) | (_, Err(e)) => {
-            println!("Parsing error for version {}: {}", name, e);
-            Ok(None)
+    Ok(report)
+}
+
+fn collect_git_requests(members: &[Member]) -> Vec<GitRequest> {
+    let mut requests = Vec::new();
+    for member in members {
+        for (section, dependencies) in &member.sections {
+            for (name, dep) in dependencies {
+                if let Some(spec) = dep.git_spec() {
+                    requests.push(GitRequest {
+                        name: name.clone(),
+                        package: dep.package().map(String::from),
+                        member: member.name.clone(),
+                        section: section.clone(),
+                        spec,
+                        registry: dep.registry().map(String::from),
+                    });
+                }
+            }
         }
     }
+    requests
 }
 
-async fn analyze_dependencies(
-    cargo_toml_path: &PathBuf,
-) -> Result<Vec<DependencyAnalysis>, Box<dyn Error>> {
-    let content = fs::read_to_string(cargo_toml_path)?;
-    println!("Cargo.toml file content read successfully");
+/// Compares git dependencies with their remote, fetching each repository once into
+/// a mirror under `mirrors`, and looks for the same crates on the registry.
+async fn analyze_git_dependencies(
+    registries: &Registries,
+    mirrors: &Path,
+    requests: Vec<GitRequest>,
+    lockfile: Option<&Lockfile>,
+    options: &AnalysisOptions,
+    offline: bool,
+) -> (Vec<GitAnalysis>, Vec<DependencyFailure>) {
+    let urls: BTreeSet<String> = requests.iter().map(|r| r.spec.url.clone()).collect();
+    let opened: Vec<_> = stream::iter(urls)
+        .map(|url| {
+            let root = mirrors.to_path_buf();
+            async move {
+                let task_url = url.clone();
+                let mirror =
+                    tokio::task::spawn_blocking(move || GitMirror::open(&root, &task_url, offline))
+                        .await
+                        .unwrap_or_else(|e| {
+                            Err(TomieError::Git {
+                                url: url.clone(),
+                                reason: e.to_string(),
+                            })
+                        });
+                (url, mirror)
+            }
+        })
+        .buffer_unordered(options.jobs.max(1))
+        .collect()
+        .await;
+    let mirrors: HashMap<String, Result<GitMirror, TomieError>> = opened.into_iter().collect();
 
-    let cargo_toml: Tomie = toml::from_str(&content)?;
-    println!("Cargo.toml file parsing successful");
+    let mut analyses = Vec::new();
+    let mut failures = Vec::new();
+    for request in requests {
+        let status = match &mirrors[&request.spec.url] {
+            Ok(mirror) => mirror.status(
+                &request.spec,
+                lockfile.and_then(|l| l.locked_commit(request.crate_name(), &request.spec.url)),
+            ),
+            Err(e) => Err(TomieError::Git {
+                url: request.spec.url.clone(),
+                reason: match e {
+                    TomieError::Git { reason, .. } => reason.clone(),
+                    e => e.to_string(),
+                },
+            }),
+        };
+        let status = match status {
+            Ok(status) => status,
+            Err(e) => {
+                log::warn!("{}: {}", request.name, e);
+                failures.push(DependencyFailure::for_git(request, &e));
+                continue;
+            }
+        };
 
-    let dependencies = match cargo_toml.dependencies {
-        Some(deps) => deps,
-        None => return Ok(vec![]),
-    };
+        // A crate first consumed from git is often published later on.
+        let crate_name = request.crate_name();
+        let registry_version = match registries.get(request.registry.as_deref()) {
+            Ok(source) => match source.list_versions(crate_name).await {
+                Ok(versions) => parse_versions(crate_name, &versions, None, options)
+                    .into_iter()
+                    .max()
+                    .map(|v| v.to_string()),
+                Err(e) => {
+                    log::debug!("{} is not on the registry: {}", crate_name, e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
 
-    println!("\nDependencies found in Cargo.toml:");
-    for (name, dep) in dependencies.iter() {
-        println!("- {}: {:?}", name, dep);
+        analyses.push(GitAnalysis {
+            name: request.name,
+            package: request.package,
+            member: request.member,
+            section: request.section,
+            git: request.spec,
+            pinned_commit: status.pinned_commit,
+            target: status.target,
+            target_commit: status.target_commit,
+            commits_behind: status.commits_behind,
+            registry_version,
+        });
     }
+    (analyses, failures)
+}
 
-    let mut futures = Vec::new();
-    for (name, dep) in dependencies {
-        if let Some(current_version) = parse_dependency_version(&dep) {
-            futures.push(analyze_dependency(name, current_version));
+/// Changelog sections of the outdated dependencies named `name`, from the version in
+/// use to the latest one. The changelog is read from `repository` when given, else
+/// from the repository the crate's metadata points at, mirrored next to the git dependencies.
+/// That metadata comes from the web API at `api_url`, which serves crates.io, or the
+/// default registry when another URL is given; crates of other registries are skipped.
+/// Failures are only logged: the rest of the explanation stands without them.
+async fn lookup_changelogs(
+    http: &HttpClient,
+    registries: &Registries,
+    analyses: &[DependencyAnalysis],
+    name: &str,
+    repository: Option<&str>,
+    api_url: &str,
+    global: &GlobalArgs,
+) -> Vec<Changelog> {
+    let offline = global.offline;
+    let api_registry = if api_url.trim_end_matches('/') == CRATES_IO_API {
+        CRATES_IO
+    } else {
+        registries.name(None)
+    };
+    let mut wanted = BTreeSet::new();
+    for analysis in analyses {
+        if !analysis.is_named(name) || !analysis.is_outdated() {
+            continue;
         }
+        let registry = registries.name(analysis.registry.as_deref());
+        if repository.is_none() && registry != api_registry {
+            log::warn!(
+                "{} comes from registry {}, which {} does not serve; pass --repository to read its changelog",
+                analysis.crate_name(),
+                registry,
+                api_url
+            );
+            continue;
+        }
+        let (Some(from), Ok(to)) = (
+            analysis.version_in_use(),
+            Version::parse(&analysis.latest_version),
+        ) else {
+            continue;
+        };
+        wanted.insert((analysis.crate_name().to_string(), from, to));
     }
 
-    let results = join_all(futures).await;
-    let analyses: Vec<_> = results
-        .into_iter()
-        .filter_map(|r| r.ok().and_then(|o| o))
-        .collect();
+    let mut changelogs = Vec::new();
+    for (crate_name, from, to) in wanted {
+        let repository = match repository {
+            Some(repository) => repository.to_string(),
+            None => match find_repository(http, api_url, &crate_name, &from, offline).await {
+                Ok(Some(repository)) => repository,
+                Ok(None) => {
+                    log::warn!(
+                        "No repository known for {}, skipping its changelog",
+                        crate_name
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Unable to find the repository of {}: {}", crate_name, e);
+                    continue;
+                }
+            },
+        };
+
+        let root = git_mirrors_dir(global);
+        let task_name = crate_name.clone();
+        let changelog = tokio::task::spawn_blocking(move || {
+            load_changelog(&root, &task_name, &repository, &from, &to, offline)
+        })
+        .await;
+        match changelog {
+            Ok(Ok(changelog)) => changelogs.push(changelog),
+            Ok(Err(e)) => log::warn!("Changelog of {}: {}", crate_name, e),
+            Err(e) => log::warn!("Changelog of {}: {}", crate_name, e),
+        }
+    }
+    changelogs
+}
+
+/// Diagnostics go to stderr at a level picked by `-q`/`-v`, overridable through `TOMIE_LOG`.
+fn init_logging(global: &GlobalArgs) {
+    let level = match (global.quiet, global.verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level.min(log::LevelFilter::Warn))
+        .filter_module(env!("CARGO_CRATE_NAME"), level)
+        .parse_env("TOMIE_LOG")
+        .target(env_logger::Target::Stderr)
+        .format_timestamp(None)
+        .init();
+}
+
+fn cache_dir(global: &GlobalArgs) -> Option<PathBuf> {
+    global.cache_dir.clone().or_else(Cache::default_dir)
+}
+
+/// Where remote git repositories are mirrored.
+fn git_mirrors_dir(global: &GlobalArgs) -> PathBuf {
+    cache_dir(global)
+        .unwrap_or_else(|| std::env::temp_dir().join("tomie"))
+        .join("git")
+}
 
-    Ok(analyses)
+fn is_broken_pipe(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+async fn main() -> ExitCode {
+    match run(Cli::parse_args()).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(1)
+        }
+    }
+}
 
-    let cargo_path = if args.len() > 1 {
-        PathBuf::from(&args[1])
-    } else {
-        PathBuf::from("Cargo.toml")
+async fn run(cli: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    let global = &cli.global;
+    init_logging(global);
+
+    let options = AnalysisOptions {
+        include_prerelease: global.include_prerelease,
+        jobs: global.jobs,
     };
 
+    let cargo_path = &global.manifest_path;
     if !cargo_path.exists() {
-        return Err("Cargo.toml file does not exist".into());
+        return Err(format!("{} does not exist", cargo_path.display()).into());
     }
 
-    println!("File analysis : {}", cargo_path.display());
-    let analyses = analyze_dependencies(&cargo_path).await?;
+    log::info!("File analysis : {}", cargo_path.display());
+    log::debug!("Options: {:?}", options);
+    let http = HttpClient::new(&HttpOptions {
+        connect_timeout: Duration::from_secs(global.connect_timeout),
+        request_timeout: Duration::from_secs(global.timeout),
+        user_agent: global.user_agent.clone(),
+        // crates.io asks API crawlers for one request per second; the sparse index is a CDN.
+        requests_per_second: global.rate.unwrap_or(match global.protocol {
+            Protocol::Sparse => 0.0,
+            Protocol::Api => 1.0,
+        }),
+        burst: global.burst,
+        retry: RetryPolicy {
+            max_attempts: global.retries.max(1),
+            base_delay: Duration::from_millis(global.retry_backoff),
+            max_delay: Duration::from_secs(30),
+            retry_statuses: global.retry_status.clone(),
+        },
+    })?;
+    let http = match cache_dir(global) {
+        Some(dir) if !global.no_cache && !global.offline => {
+            let mode = if global.refresh {
+                CacheMode::Refresh
+            } else {
+                CacheMode::Normal
+            };
+            log::debug!("Registry cache in {}", dir.display());
+            http.with_cache(Cache::new(dir, Duration::from_secs(global.cache_ttl), mode))
+        }
+        _ => http,
+    };
+    let members = load_members(cargo_path)?;
+    let requests = collect_requests(&members);
+    let git_requests = collect_git_requests(&members);
 
-    println!("\nAnalysis :");
-    println!("------------------------");
+    let manifest_dir = cargo_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let config = CargoConfig::load(manifest_dir)?;
+    let default_registry = global
+        .registry
+        .clone()
+        .unwrap_or_else(|| config.default_registry());
+    let registries = match &global.registry_fixture {
+        Some(path) => {
+            log::info!("Serving every registry from {}", path.display());
+            Registries::single(default_registry, Arc::new(MemorySource::from_file(path)?))
+        }
+        None => Registries::resolve(
+            &http,
+            global.protocol,
+            &config,
+            default_registry,
+            requests.iter().filter_map(|r| r.registry.clone()),
+            global.offline,
+        ),
+    };
 
-    if analyses.is_empty() {
-        println!("No dependencies analyzed successfully.");
-        return Ok(());
+    let lockfile = Lockfile::load(manifest_dir)?;
+    if lockfile.is_none() {
+        log::info!("No Cargo.lock in {}", manifest_dir.display());
+    }
+    let mut report =
+        analyze_dependencies(&registries, requests, lockfile.as_ref(), &options).await?;
+    report.path_mismatches = path_mismatches(&members);
+    if !git_requests.is_empty() {
+        let (analyses, failures) = analyze_git_dependencies(
+            &registries,
+            &git_mirrors_dir(global),
+            git_requests,
+            lockfile.as_ref(),
+            &options,
+            global.offline,
+        )
+        .await;
+        report.git_dependencies = analyses;
+        report.failures.extend(failures);
     }
 
-    for analysis in analyses {
-        println!(
-            "{}: {} -> {} {}",
-            analysis.name,
-            analysis.current_version,
-            analysis.latest_version,
-            if analysis.is_outdated {
-                "(obsolete)"
+    let printed = match cli.command.unwrap_or(Command::Check) {
+        Command::Check => match global.format {
+            OutputFormat::Text => print_text_report(&report),
+            OutputFormat::Json => print_json_report(cargo_path, &report),
+        },
+        Command::Update {
+            crates,
+            breaking,
+            write,
+        } => {
+            let updates = propose_updates(&report.dependencies, &crates, breaking);
+            let edits = edit_manifests(&updates, cargo_path)?;
+            if write {
+                for edit in &edits {
+                    edit.write()?;
+                    log::info!("Updated {}", edit.path.display());
+                }
+                print_updates(&updates, global.format)
+            } else if global.format == OutputFormat::Json {
+                print_updates(&updates, global.format)
             } else {
-                "(Up to date)"
+                print_diffs(&edits, manifest_dir)
             }
+        }
+        Command::Diff { crates, breaking } => {
+            let updates = propose_updates(&report.dependencies, &crates, breaking);
+            let edits = edit_manifests(&updates, cargo_path)?;
+            print_review(&updates, &edits, manifest_dir, global.format)
+        }
+        Command::Interactive => match pick_updates(&report.dependencies)? {
+            Some(updates) => {
+                for edit in edit_manifests(&updates, cargo_path)? {
+                    edit.write()?;
+                    log::info!("Updated {}", edit.path.display());
+                }
+                print_updates(&updates, global.format)
+            }
+            None => writeln!(io::stdout().lock(), "No manifest changed.").map_err(Into::into),
+        },
+        Command::Explain {
+            name,
+            no_changelog,
+            repository,
+            api_url,
+        } => {
+            let changelogs = if no_changelog {
+                Vec::new()
+            } else {
+                lookup_changelogs(
+                    &http,
+                    &registries,
+                    &report.dependencies,
+                    &name,
+                    repository.as_deref(),
+                    &api_url,
+                    global,
+                )
+                .await
+            };
+            print_explain(cargo_path, &name, &report, &changelogs, global.format)
+        }
+    };
+    // Output cut short by its reader, e.g. `| head`, is not a failure.
+    match printed {
+        Err(e) if is_broken_pipe(e.as_ref()) => log::debug!("Output closed early: {}", e),
+        printed => printed?,
+    }
+
+    let severity = report.severity();
+    log::debug!("Analysis severity: {:?}", severity);
+    Ok(ExitCode::from(global.fail_on.exit_code(severity)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(nums: &[&str]) -> Vec<Version> {
+        nums.iter().map(|v| Version::parse(v).unwrap()).collect()
+    }
+
+    fn req(text: &str) -> VersionReq {
+        VersionReq::parse(text).unwrap()
+    }
+
+    fn published(nums: &[(&str, bool)]) -> Vec<CrateVersion> {
+        nums.iter()
+            .map(|(num, yanked)| CrateVersion {
+                num: num.to_string(),
+                yanked: *yanked,
+                rust_version: None,
+                features: BTreeMap::new(),
+                dependencies: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn request(
+        name: &str,
+        package: Option<&str>,
+        member: &str,
+        version: &str,
+    ) -> DependencyRequest {
+        DependencyRequest {
+            name: name.to_string(),
+            package: package.map(String::from),
+            member: member.to_string(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", member)),
+            from_workspace: false,
+            section: DependencySection {
+                kind: DependencyKind::Normal,
+                target: None,
+            },
+            current_version: version.to_string(),
+            registry: None,
+        }
+    }
+
+    const OPTIONS: AnalysisOptions = AnalysisOptions {
+        include_prerelease: false,
+        jobs: 2,
+    };
+
+    #[test]
+    fn pinned_version_needs_a_full_version() {
+        assert_eq!(pinned_version(&req("=1.2.3")), Some(Version::new(1, 2, 3)));
+        assert_eq!(pinned_version(&req("1.2.3")), Some(Version::new(1, 2, 3)));
+        assert_eq!(
+            pinned_version(&req("=2.0.0-rc.1")),
+            Some(Version::parse("2.0.0-rc.1").unwrap())
         );
+        assert_eq!(pinned_version(&req("1.2")), None);
+        assert_eq!(pinned_version(&req("*")), None);
+    }
+
+    #[test]
+    fn parse_versions_keeps_pre_releases_the_requirement_names() {
+        let published = published(&[
+            ("1.5.0", false),
+            ("2.0.0-rc.1", false),
+            ("2.0.0-rc.2", true),
+            ("3.0.0-alpha.1", false),
+        ]);
+        let parsed = parse_versions("foo", &published, Some(&req("2.0.0-rc.1")), &OPTIONS);
+        assert_eq!(parsed, versions(&["1.5.0", "2.0.0-rc.1"]));
+        let parsed = parse_versions("foo", &published, Some(&req("1.5")), &OPTIONS);
+        assert_eq!(parsed, versions(&["1.5.0"]));
+    }
+
+    #[test]
+    fn classify_up_to_date_when_only_the_newest_matches() {
+        let (compatible, latest, status) =
+            classify(&req("2.1"), &versions(&["1.0.0", "2.0.0", "2.1.0"])).unwrap();
+        assert_eq!(compatible, Some(Version::new(2, 1, 0)));
+        assert_eq!(latest, Version::new(2, 1, 0));
+        assert_eq!(status, UpdateStatus::UpToDate);
+    }
+
+    #[test]
+    fn classify_compatible_when_the_requirement_accepts_the_newest() {
+        let (compatible, latest, status) =
+            classify(&req("1.0"), &versions(&["1.0.0", "1.4.2", "0.9.0"])).unwrap();
+        assert_eq!(compatible, Some(Version::new(1, 4, 2)));
+        assert_eq!(latest, Version::new(1, 4, 2));
+        assert_eq!(status, UpdateStatus::CompatibleUpdate);
+    }
+
+    #[test]
+    fn classify_breaking_when_the_newest_is_out_of_range() {
+        let (compatible, latest, status) =
+            classify(&req("0.11"), &versions(&["0.11.3", "0.12.0", "0.11.27"])).unwrap();
+        assert_eq!(compatible, Some(Version::new(0, 11, 27)));
+        assert_eq!(latest, Version::new(0, 12, 0));
+        assert_eq!(status, UpdateStatus::BreakingUpdate);
+
+        let (compatible, _, status) =
+            classify(&req("=1.0.0"), &versions(&["0.9.0", "2.0.0"])).unwrap();
+        assert_eq!(compatible, None);
+        assert_eq!(status, UpdateStatus::BreakingUpdate);
+
+        assert!(classify(&req("1"), &[]).is_none());
+    }
+
+    #[test]
+    fn classify_never_reports_a_release_older_than_the_requirement() {
+        let (compatible, latest, status) =
+            classify(&req("2.0.0-rc.1"), &versions(&["1.5.0", "2.0.0-rc.1"])).unwrap();
+        assert_eq!(compatible, Some(Version::parse("2.0.0-rc.1").unwrap()));
+        assert_eq!(latest, Version::parse("2.0.0-rc.1").unwrap());
+        assert_eq!(status, UpdateStatus::UpToDate);
+
+        assert!(classify(&req("2.0.0-rc.1"), &versions(&["1.5.0"])).is_none());
     }
 
-    Ok(())
+    #[test]
+    fn upgrade_candidates_keep_the_newest_of_each_line() {
+        let all = versions(&[
+            "0.11.0", "0.12.1", "0.12.28", "0.13.0", "0.13.5", "1.0.0", "1.2.0", "0.0.3", "0.0.4",
+        ]);
+        let candidates = upgrade_candidates(&all, &req("0.12"), Some(&Version::new(0, 12, 28)));
+        assert_eq!(candidates, versions(&["0.12.28", "0.13.5", "1.2.0"]));
+
+        // Every 0.0.x release is a line of its own.
+        let candidates = upgrade_candidates(&all, &req("0.0.3"), Some(&Version::new(0, 0, 3)));
+        assert_eq!(candidates[..2], versions(&["0.0.3", "0.0.4"]));
+    }
+
+    #[test]
+    fn upgrade_candidates_without_compatible_release_stay_above_the_requirement() {
+        // `=1.0.0` was yanked, so nothing published matches it any more.
+        let all = versions(&["0.9.0", "1.1.0", "2.0.0"]);
+        let candidates = upgrade_candidates(&all, &req("=1.0.0"), None);
+        assert_eq!(candidates, versions(&["1.1.0", "2.0.0"]));
+
+        let candidates = upgrade_candidates(&all, &req(">=1.0, <1.1"), None);
+        assert_eq!(candidates, versions(&["1.1.0", "2.0.0"]));
+    }
+
+    #[test]
+    fn git_and_path_dependencies_skip_the_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+            [package]
+            name = "app"
+            version = "0.1.0"
+
+            [dependencies]
+            serde = "1"
+            forked = { git = "https://github.com/owner/forked", version = "1.0" }
+            "#,
+        )
+        .unwrap();
+        let members = load_members(&manifest).unwrap();
+
+        let requests = collect_requests(&members);
+        let names: Vec<&str> = requests.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["serde"]);
+        let git = collect_git_requests(&members);
+        assert_eq!(git.len(), 1);
+        assert_eq!(git[0].name, "forked");
+    }
+
+    #[tokio::test]
+    async fn analyze_dependencies_from_memory() {
+        let source = MemorySource::new(HashMap::from([
+            (
+                "serde".to_string(),
+                published(&[("1.0.100", true), ("1.0.200", false), ("2.0.0-rc.1", false)]),
+            ),
+            (
+                "tokio".to_string(),
+                published(&[("0.3.7", false), ("1.41.0", false)]),
+            ),
+        ]));
+        let registries = Registries::single("crates-io".to_string(), Arc::new(source));
+        let requests = vec![
+            request("serde", None, "app", "=1.0.100"),
+            request("serde", None, "lib", "1.0.200"),
+            request("tokio03", Some("tokio"), "app", "0.3"),
+            request("missing", None, "app", "1"),
+        ];
+
+        let report = analyze_dependencies(&registries, requests, None, &OPTIONS)
+            .await
+            .unwrap();
+
+        // serde is looked up once for both members.
+        assert_eq!(report.lookups.total, 3);
+        assert_eq!(report.lookups.failed, 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].name, "missing");
+
+        let pinned = &report.dependencies[0];
+        assert!(pinned.pinned_yanked);
+        assert_eq!(pinned.compatible_version, None);
+        assert_eq!(pinned.latest_version, "1.0.200");
+        assert_eq!(pinned.status, UpdateStatus::BreakingUpdate);
+        assert_eq!(pinned.candidates, ["1.0.200"]);
+
+        let current = &report.dependencies[1];
+        assert!(!current.pinned_yanked);
+        assert_eq!(current.status, UpdateStatus::UpToDate);
+        assert_eq!(current.action, UpdateAction::None);
+
+        let alias = &report.dependencies[2];
+        assert_eq!(alias.name, "tokio03");
+        assert_eq!(alias.compatible_version.as_deref(), Some("0.3.7"));
+        assert_eq!(alias.latest_version, "1.41.0");
+        assert_eq!(alias.action, UpdateAction::EditManifest);
+    }
 }
@@ -0,0 +1,66 @@
+use super::http::HttpClient;
+use super::RegistrySource;
+use crate::error::TomieError;
+use crate::utils::CrateVersion;
+use futures::future::BoxFuture;
+
+/// The crates.io web API, `{base}/api/v1/crates/{name}`; one JSON document per crate.
+#[derive(Debug)]
+pub struct ApiSource {
+    http: HttpClient,
+    base_url: String,
+}
+
+impl ApiSource {
+    pub fn new(http: HttpClient, base_url: String) -> Self {
+        ApiSource { http, base_url }
+    }
+}
+
+impl RegistrySource for ApiSource {
+    fn list_versions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<CrateVersion>, TomieError>> {
+        Box::pin(async move {
+            let url = format!("{}/api/v1/crates/{}", self.base_url, name);
+            self.http
+                .fetch_versions(&self.base_url, name, &url, parse_api_versions)
+                .await
+        })
+    }
+
+    fn retried_lookups(&self) -> usize {
+        self.http.retried_lookups()
+    }
+}
+
+/// Reads the `versions` array of a crates.io web API response.
+fn parse_api_versions(name: &str, body: &[u8]) -> Result<Vec<CrateVersion>, TomieError> {
+    let json: serde_json::Value =
+        serde_json::from_slice(body).map_err(|e| TomieError::JsonShape {
+            name: name.to_string(),
+            reason: e.to_string(),
+        })?;
+
+    let versions = json["versions"]
+        .as_array()
+        .ok_or_else(|| TomieError::JsonShape {
+            name: name.to_string(),
+            reason: "missing versions array".to_string(),
+        })?
+        .iter()
+        .filter_map(|v| {
+            let num = v["num"].as_str()?;
+            Some(CrateVersion {
+                num: num.to_string(),
+                yanked: v["yanked"].as_bool().unwrap_or(false),
+                rust_version: v["rust_version"].as_str().map(String::from),
+                features: serde_json::from_value(v["features"].clone()).unwrap_or_default(),
+                dependencies: Vec::new(),
+            })
+        })
+        .collect();
+
+    Ok(versions)
+}
@@ -0,0 +1,191 @@
+use crate::utils::CrateVersion;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Serve fresh entries, revalidate stale ones.
+    Normal,
+    /// Revalidate every entry whatever its age.
+    Refresh,
+}
+
+/// Registry metadata of one crate as last received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Seconds since the Unix epoch at which the registry last confirmed the entry.
+    pub fetched_at: u64,
+    pub versions: Vec<CrateVersion>,
+}
+
+impl CacheEntry {
+    pub fn new(
+        etag: Option<String>,
+        last_modified: Option<String>,
+        versions: Vec<CrateVersion>,
+    ) -> Self {
+        CacheEntry {
+            etag,
+            last_modified,
+            fetched_at: now(),
+            versions,
+        }
+    }
+
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+
+    pub fn touch(&mut self) {
+        self.fetched_at = now();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// On-disk cache of registry responses, one JSON file per registry and crate.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    pub ttl: Duration,
+    pub mode: CacheMode,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration, mode: CacheMode) -> Self {
+        Cache { dir, ttl, mode }
+    }
+
+    /// `tomie` under the user cache directory, e.g. `~/.cache/tomie`.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("tomie"))
+    }
+
+    fn path(&self, registry: &str, name: &str) -> PathBuf {
+        let registry: String = registry
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir
+            .join(registry)
+            .join(format!("{}.json", name.to_lowercase()))
+    }
+
+    pub fn load(&self, registry: &str, name: &str) -> Option<CacheEntry> {
+        let path = self.path(registry, name);
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                log::debug!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Whether `entry` can be used without asking the registry.
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        self.mode == CacheMode::Normal && entry.age() < self.ttl
+    }
+
+    pub fn store(&self, registry: &str, name: &str, entry: &CacheEntry) -> io::Result<()> {
+        let path = self.path(registry, name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string(entry).map_err(io::Error::other)?;
+        // Written aside then renamed so concurrent runs never read half a file.
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, content)?;
+        fs::rename(&partial, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn entry(versions: &[&str]) -> CacheEntry {
+        let versions = versions
+            .iter()
+            .map(|num| CrateVersion {
+                num: num.to_string(),
+                yanked: false,
+                rust_version: None,
+                features: BTreeMap::new(),
+                dependencies: Vec::new(),
+            })
+            .collect();
+        CacheEntry::new(Some("\"abc\"".to_string()), None, versions)
+    }
+
+    #[test]
+    fn freshness_follows_ttl_and_mode() {
+        let cache = Cache::new(PathBuf::new(), Duration::from_secs(60), CacheMode::Normal);
+        let mut entry = entry(&["1.0.0"]);
+        assert!(cache.is_fresh(&entry));
+
+        entry.fetched_at -= 120;
+        assert!(!cache.is_fresh(&entry));
+        entry.touch();
+        assert!(cache.is_fresh(&entry));
+
+        let refresh = Cache::new(PathBuf::new(), Duration::from_secs(60), CacheMode::Refresh);
+        assert!(!refresh.is_fresh(&entry));
+    }
+
+    #[test]
+    fn one_file_per_registry_and_crate() {
+        let cache = Cache::new(PathBuf::from("/cache"), Duration::ZERO, CacheMode::Normal);
+        assert_eq!(
+            cache.path("https://index.crates.io/", "Serde"),
+            PathBuf::from("/cache/index.crates.io_/serde.json")
+        );
+        assert_eq!(
+            cache.path("sparse+https://cargo.example.com:8443/index", "rand"),
+            PathBuf::from("/cache/sparse_https___cargo.example.com_8443_index/rand.json")
+        );
+    }
+
+    #[test]
+    fn stored_entries_load_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(
+            dir.path().to_path_buf(),
+            Duration::from_secs(60),
+            CacheMode::Normal,
+        );
+        let registry = "https://index.crates.io/";
+        assert!(cache.load(registry, "serde").is_none());
+
+        cache
+            .store(registry, "serde", &entry(&["1.0.0", "1.0.1"]))
+            .unwrap();
+        let loaded = cache.load(registry, "serde").unwrap();
+        assert_eq!(loaded.etag.as_deref(), Some("\"abc\""));
+        let versions: Vec<&str> = loaded.versions.iter().map(|v| v.num.as_str()).collect();
+        assert_eq!(versions, ["1.0.0", "1.0.1"]);
+
+        fs::write(cache.path(registry, "serde"), "{ not json").unwrap();
+        assert!(cache.load(registry, "serde").is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use super::cache::{Cache, CacheEntry};
+use super::rate_limit::{retry_after, RateLimiter};
+use super::retry::{RetryPolicy, RetryStats};
+use crate::error::TomieError;
+use crate::utils::CrateVersion;
+use reqwest::header::{self, HeaderMap, HeaderValue};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A response read to the end.
+struct Response {
+    status: reqwest::StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub user_agent: String,
+    /// Average request rate, 0 for none.
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub retry: RetryPolicy,
+}
+
+/// HTTP plumbing of the remote sources: one pooled client, rate limit,
+/// retries and cache, shared by every source of a run.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    /// Sent as `Authorization` to private registries.
+    token: Option<String>,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    stats: Arc<RetryStats>,
+    cache: Option<Cache>,
+}
+
+impl HttpClient {
+    pub fn new(options: &HttpOptions) -> Result<Self, TomieError> {
+        let client = reqwest::Client::builder()
+            .user_agent(&options.user_agent)
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout)
+            .build()
+            .map_err(|source| TomieError::HttpClient { source })?;
+
+        Ok(HttpClient {
+            client,
+            token: None,
+            limiter: Arc::new(RateLimiter::new(options.requests_per_second, options.burst)),
+            retry: options.retry.clone(),
+            stats: Arc::default(),
+            cache: None,
+        })
+    }
+
+    /// Keeps registry responses in `cache` between runs.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// A client for one source: same connections and limits, its own statistics.
+    pub fn for_source(&self) -> Self {
+        HttpClient {
+            token: None,
+            stats: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    /// The same client, sending `token` as `Authorization` with every request.
+    pub fn with_token(&self, token: String) -> Self {
+        HttpClient {
+            token: Some(token),
+            ..self.clone()
+        }
+    }
+
+    /// Number of lookups so far that needed to be retried.
+    pub fn retried_lookups(&self) -> usize {
+        self.stats.retried()
+    }
+
+    /// Sends a rate-limited GET and reads the response, retrying transient failures
+    /// with backoff and waiting out 429 responses as long as `Retry-After` says.
+    async fn send(
+        &self,
+        name: &str,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> Result<Response, TomieError> {
+        let mut attempt = 1;
+        loop {
+            self.limiter.acquire().await;
+            let last_attempt = attempt >= self.retry.max_attempts;
+
+            // A connection may also be reset or time out while the body is read.
+            let result = match self.client.get(url).headers(headers.clone()).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    let headers = response.headers().clone();
+                    response.bytes().await.map(|body| Response {
+                        status,
+                        headers,
+                        body: body.to_vec(),
+                    })
+                }
+                Err(source) => Err(source),
+            };
+            let delay = match result {
+                Ok(response) if response.status == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    if last_attempt {
+                        return Ok(response);
+                    }
+                    let delay = retry_after(&response.headers)
+                        .unwrap_or_else(|| self.retry.backoff(attempt));
+                    self.limiter.pause(delay).await;
+                    log::warn!(
+                        "Rate limited while looking up {}, retrying in {:?}",
+                        name,
+                        delay
+                    );
+                    delay
+                }
+                Ok(response) if self.retry.is_transient_status(response.status) => {
+                    if last_attempt {
+                        return Ok(response);
+                    }
+                    let delay = self.retry.backoff(attempt);
+                    log::warn!(
+                        "HTTP {} while looking up {}, retrying in {:?}",
+                        response.status.as_u16(),
+                        name,
+                        delay
+                    );
+                    delay
+                }
+                Ok(response) => return Ok(response),
+                Err(source) if !last_attempt && self.retry.is_transient_error(&source) => {
+                    let delay = self.retry.backoff(attempt);
+                    log::warn!(
+                        "Request for {} failed ({}), retrying in {:?}",
+                        name,
+                        source,
+                        delay
+                    );
+                    delay
+                }
+                Err(source) => {
+                    return Err(TomieError::Network {
+                        name: name.to_string(),
+                        source,
+                    })
+                }
+            };
+
+            if attempt == 1 {
+                self.stats.record_retried();
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Fetches a JSON document about `name`, bypassing the cache.
+    pub async fn get_json(&self, name: &str, url: &str) -> Result<serde_json::Value, TomieError> {
+        log::debug!("Request for {}: {}", name, url);
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.token {
+            insert_header(&mut headers, header::AUTHORIZATION, Some(token));
+        }
+        let response = self.send(name, url, &headers).await?;
+        let status = response.status;
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(TomieError::CrateNotFound {
+                name: name.to_string(),
+            });
+        }
+        if !status.is_success() {
+            return Err(TomieError::HttpStatus {
+                name: name.to_string(),
+                status,
+            });
+        }
+        serde_json::from_slice(&response.body).map_err(|e| TomieError::JsonShape {
+            name: name.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Fetches the versions of `name` from `url`, going through the cache under
+    /// `cache_key` and revalidating stale entries with ETag / Last-Modified.
+    pub async fn fetch_versions(
+        &self,
+        cache_key: &str,
+        name: &str,
+        url: &str,
+        parse: impl FnOnce(&str, &[u8]) -> Result<Vec<CrateVersion>, TomieError>,
+    ) -> Result<Vec<CrateVersion>, TomieError> {
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.load(cache_key, name));
+        if let (Some(cache), Some(entry)) = (&self.cache, &cached) {
+            if cache.is_fresh(entry) {
+                log::debug!("Cache hit for {} ({:?} old)", name, entry.age());
+                return Ok(entry.versions.clone());
+            }
+        }
+
+        log::debug!("Request to registry for {}: {}", name, url);
+
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.token {
+            insert_header(&mut headers, header::AUTHORIZATION, Some(token));
+        }
+        if let Some(entry) = &cached {
+            insert_header(&mut headers, header::IF_NONE_MATCH, entry.etag.as_deref());
+            insert_header(
+                &mut headers,
+                header::IF_MODIFIED_SINCE,
+                entry.last_modified.as_deref(),
+            );
+        }
+        let response = self.send(name, url, &headers).await?;
+
+        let status = response.status;
+        if let (reqwest::StatusCode::NOT_MODIFIED, Some(mut entry)) = (status, cached) {
+            log::debug!("{} not modified since the cached copy", name);
+            entry.touch();
+            self.store(cache_key, name, &entry);
+            return Ok(entry.versions);
+        }
+        // Sparse registries may also answer 410 or 451 for crates they do not serve.
+        if matches!(status.as_u16(), 404 | 410 | 451) {
+            return Err(TomieError::CrateNotFound {
+                name: name.to_string(),
+            });
+        }
+        if !status.is_success() {
+            return Err(TomieError::HttpStatus {
+                name: name.to_string(),
+                status,
+            });
+        }
+
+        let etag = header_string(&response.headers, header::ETAG);
+        let last_modified = header_string(&response.headers, header::LAST_MODIFIED);
+        let versions = parse(name, &response.body)?;
+        self.store(
+            cache_key,
+            name,
+            &CacheEntry::new(etag, last_modified, versions.clone()),
+        );
+        Ok(versions)
+    }
+
+    fn store(&self, cache_key: &str, name: &str, entry: &CacheEntry) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.store(cache_key, name, entry) {
+                log::warn!("Unable to cache registry metadata of {}: {}", name, e);
+            }
+        }
+    }
+}
+
+fn insert_header(headers: &mut HeaderMap, name: header::HeaderName, value: Option<&str>) {
+    if let Some(value) = value.and_then(|v| HeaderValue::from_str(v).ok()) {
+        headers.insert(name, value);
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
@@ -0,0 +1,115 @@
+use crate::utils::{CrateVersion, CrateVersionDependency};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// One line of a registry index file, shared by the sparse protocol and cargo's local copies.
+#[derive(Debug, Deserialize)]
+pub struct IndexLine {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+    #[serde(default)]
+    deps: Vec<IndexDependency>,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    /// Features using the `dep:` or `?` syntax, kept apart for older cargo.
+    #[serde(default)]
+    features2: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    rust_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexDependency {
+    name: String,
+    req: String,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    package: Option<String>,
+}
+
+impl From<IndexLine> for CrateVersion {
+    fn from(line: IndexLine) -> Self {
+        let mut features = line.features;
+        features.extend(line.features2);
+
+        CrateVersion {
+            num: line.vers,
+            yanked: line.yanked,
+            rust_version: line.rust_version,
+            features,
+            dependencies: line
+                .deps
+                .into_iter()
+                .map(|dep| CrateVersionDependency {
+                    name: dep.name,
+                    req: dep.req,
+                    kind: dep.kind,
+                    optional: dep.optional,
+                    target: dep.target,
+                    package: dep.package,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Parses newline-delimited index JSON, skipping lines that do not parse.
+pub fn parse_lines<'a>(name: &str, lines: impl IntoIterator<Item = &'a [u8]>) -> Vec<CrateVersion> {
+    lines
+        .into_iter()
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .filter_map(|line| match serde_json::from_slice::<IndexLine>(line) {
+            Ok(line) => Some(line.into()),
+            Err(e) => {
+                log::debug!("Skipping index line of {}: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Location of a crate inside an index, e.g. `se/rd/serde` or `3/s/syn`.
+pub fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_path_by_name_length() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("syn"), "3/s/syn");
+        assert_eq!(index_path("serde"), "se/rd/serde");
+        assert_eq!(index_path("Inflector"), "in/fl/inflector");
+    }
+
+    #[test]
+    fn parse_lines_skips_what_does_not_parse() {
+        let lines = [
+            &br#"{"name":"demo","vers":"1.0.0","deps":[],"features":{},"yanked":false}"#[..],
+            b"",
+            b"not json",
+            br#"{"name":"demo","vers":"1.1.0","deps":[],"features":{},"yanked":true,"rust_version":"1.70"}"#,
+        ];
+        let versions = parse_lines("demo", lines);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].num, "1.0.0");
+        assert!(versions[1].yanked);
+        assert_eq!(versions[1].rust_version.as_deref(), Some("1.70"));
+    }
+}
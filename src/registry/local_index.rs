@@ -0,0 +1,164 @@
+use super::index::{index_path, parse_lines};
+use super::RegistrySource;
+use crate::config::cargo_home;
+use crate::error::TomieError;
+use crate::utils::CrateVersion;
+use futures::future::BoxFuture;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Version metadata read from cargo's own copy of the registry index, for machines without network.
+#[derive(Debug)]
+pub struct LocalIndex {
+    roots: Vec<PathBuf>,
+    /// Modification time of the oldest index file used so far.
+    oldest: Mutex<Option<SystemTime>>,
+}
+
+impl LocalIndex {
+    /// Finds the index directories under `$CARGO_HOME/registry/index` whose name starts
+    /// with one of `prefixes`; cargo names them after the index host, e.g.
+    /// `index.crates.io-<hash>` for the sparse protocol and `github.com-<hash>` for git.
+    pub fn discover(prefixes: &[String]) -> Result<Self, TomieError> {
+        let index_dir = cargo_home()
+            .ok_or(TomieError::NoLocalIndex {
+                path: PathBuf::from("$CARGO_HOME"),
+            })?
+            .join("registry")
+            .join("index");
+
+        let roots: Vec<PathBuf> = fs::read_dir(&index_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir() && has_prefix(path, prefixes))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if roots.is_empty() {
+            return Err(TomieError::NoLocalIndex { path: index_dir });
+        }
+        log::debug!("Local registry index directories: {:?}", roots);
+        Ok(LocalIndex {
+            roots,
+            oldest: Mutex::new(None),
+        })
+    }
+
+    /// Modification time of the oldest index file a lookup relied on.
+    fn oldest_data(&self) -> Option<SystemTime> {
+        *self.oldest.lock().unwrap()
+    }
+
+    fn get_crate_versions(&self, name: &str) -> Result<Vec<CrateVersion>, TomieError> {
+        let relative = index_path(name);
+
+        // Each root may hold the crate in its `.cache` (both protocols) or, for an
+        // old git checkout, as a plain file; the most recently written one wins.
+        let newest = self
+            .roots
+            .iter()
+            .flat_map(|root| [root.join(".cache").join(&relative), root.join(&relative)])
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, modified))
+            })
+            .max_by_key(|(_, modified)| *modified);
+
+        let Some((path, modified)) = newest else {
+            return Err(TomieError::CrateNotFound {
+                name: name.to_string(),
+            });
+        };
+        log::debug!("Reading {} from {}", name, path.display());
+
+        let content = fs::read(&path).map_err(|source| TomieError::ManifestIo {
+            path: path.clone(),
+            source,
+        })?;
+        let lines = if path.components().any(|c| c.as_os_str() == ".cache") {
+            cache_lines(&content)
+        } else {
+            content.split(|b| *b == b'\n').collect()
+        };
+
+        let versions = parse_lines(name, lines);
+
+        let mut oldest = self.oldest.lock().unwrap();
+        if oldest.is_none_or(|oldest| modified < oldest) {
+            *oldest = Some(modified);
+        }
+        Ok(versions)
+    }
+}
+
+impl RegistrySource for LocalIndex {
+    fn list_versions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<CrateVersion>, TomieError>> {
+        let versions = self.get_crate_versions(name);
+        Box::pin(async move { versions })
+    }
+
+    fn data_age(&self) -> Option<Duration> {
+        let oldest = self.oldest_data()?;
+        Some(oldest.elapsed().unwrap_or(Duration::ZERO))
+    }
+}
+
+fn has_prefix(path: &Path, prefixes: &[String]) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .is_some_and(|name| {
+            prefixes
+                .iter()
+                .any(|prefix| name.starts_with(prefix.as_str()))
+        })
+}
+
+/// Extracts the JSON lines of a cargo index cache file.
+///
+/// The file starts with a cache version byte and a little-endian `u32` index
+/// version, followed by NUL-terminated strings: the index revision, then
+/// alternating version numbers and their JSON line.
+fn cache_lines(content: &[u8]) -> Vec<&[u8]> {
+    if content.len() < 5 {
+        return Vec::new();
+    }
+    let mut fields = content[5..].split(|b| *b == 0);
+    fields.next(); // index revision
+    fields
+        .skip(1)
+        .step_by(2)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_lines_skips_header_and_versions() {
+        let mut content = vec![3, 2, 0, 0, 0];
+        content.extend_from_slice(b"etag: \"abc\"\0");
+        content.extend_from_slice(b"1.0.0\0{\"vers\":\"1.0.0\"}\0");
+        content.extend_from_slice(b"1.1.0\0{\"vers\":\"1.1.0\"}\0");
+        assert_eq!(
+            cache_lines(&content),
+            [&b"{\"vers\":\"1.0.0\"}"[..], b"{\"vers\":\"1.1.0\"}"]
+        );
+    }
+
+    #[test]
+    fn cache_lines_of_truncated_files() {
+        assert!(cache_lines(&[3, 2]).is_empty());
+        assert!(cache_lines(&[3, 2, 0, 0, 0]).is_empty());
+        assert!(cache_lines(b"\x03\x02\0\0\0rev\0").is_empty());
+    }
+}
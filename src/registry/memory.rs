@@ -0,0 +1,48 @@
+use super::RegistrySource;
+use crate::error::TomieError;
+use crate::utils::CrateVersion;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Versions held in memory, e.g. loaded from a JSON fixture mapping crate names to
+/// their versions; lets the analysis run without any registry.
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    crates: HashMap<String, Vec<CrateVersion>>,
+}
+
+impl MemorySource {
+    pub fn new(crates: HashMap<String, Vec<CrateVersion>>) -> Self {
+        MemorySource { crates }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, TomieError> {
+        let content = fs::read(path).map_err(|source| TomieError::ManifestIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let crates = serde_json::from_slice(&content).map_err(|e| TomieError::JsonShape {
+            name: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(MemorySource::new(crates))
+    }
+}
+
+impl RegistrySource for MemorySource {
+    fn list_versions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<CrateVersion>, TomieError>> {
+        let versions = self
+            .crates
+            .get(name)
+            .cloned()
+            .ok_or_else(|| TomieError::CrateNotFound {
+                name: name.to_string(),
+            });
+        Box::pin(async move { versions })
+    }
+}
@@ -0,0 +1,222 @@
+mod api;
+mod cache;
+mod http;
+mod index;
+mod local_index;
+mod memory;
+mod rate_limit;
+mod retry;
+mod sparse;
+
+use crate::config::{CargoConfig, IndexLocation};
+use crate::error::TomieError;
+use crate::utils::*;
+pub use api::ApiSource;
+pub use cache::{Cache, CacheMode};
+use futures::future::BoxFuture;
+pub use http::{HttpClient, HttpOptions};
+pub use local_index::LocalIndex;
+pub use memory::MemorySource;
+pub use retry::{RetryPolicy, DEFAULT_RETRY_STATUSES};
+pub use sparse::SparseSource;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const CRATES_IO_API: &str = "https://crates.io";
+pub const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io";
+
+/// How version metadata is fetched from a remote registry.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Protocol {
+    /// The sparse index: one newline-delimited JSON file per crate, served from a CDN.
+    #[default]
+    Sparse,
+    /// The crates.io web API; heavier and limited to one request per second.
+    Api,
+}
+
+/// crates.io asks automated clients to identify themselves with a way to reach the operator.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "TomieChecker/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/zxfae/TomieChecker)"
+);
+
+/// A backend answering version lookups: the web API, a sparse index, cargo's
+/// local index or an in-memory fixture.
+pub trait RegistrySource: fmt::Debug + Send + Sync {
+    /// Every published version of `name`, yanked ones included.
+    fn list_versions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<CrateVersion>, TomieError>>;
+
+    /// Age of the oldest data served so far, for sources that may be stale.
+    fn data_age(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Number of lookups so far that needed to be retried.
+    fn retried_lookups(&self) -> usize {
+        0
+    }
+}
+
+/// Every registry the analyzed manifests refer to, by the name used in their `registry` keys.
+#[derive(Debug)]
+pub struct Registries {
+    default: String,
+    registries: HashMap<String, Result<Arc<dyn RegistrySource>, String>>,
+    /// Answers for any registry not in `registries`.
+    fallback: Option<Arc<dyn RegistrySource>>,
+}
+
+impl Registries {
+    /// Resolves each registry name through the cargo configuration; remote sources
+    /// share `http`, and crates.io is reached through `protocol`.
+    pub fn resolve(
+        http: &HttpClient,
+        protocol: Protocol,
+        config: &CargoConfig,
+        default: String,
+        names: impl IntoIterator<Item = String>,
+        offline: bool,
+    ) -> Self {
+        let mut registries = HashMap::new();
+        for name in names.into_iter().chain([default.clone()]) {
+            if registries.contains_key(&name) {
+                continue;
+            }
+            let registry =
+                Self::resolve_one(http, protocol, config, &name, offline).map_err(|e| match e {
+                    TomieError::RegistryConfig { reason, .. } => reason,
+                    e => e.to_string(),
+                });
+            if let Err(reason) = &registry {
+                log::warn!("Unable to use registry {}: {}", name, reason);
+            }
+            registries.insert(name, registry);
+        }
+        Registries {
+            default,
+            registries,
+            fallback: None,
+        }
+    }
+
+    /// Serves every registry from `source`, whatever the manifests and configuration say.
+    pub fn single(default: String, source: Arc<dyn RegistrySource>) -> Self {
+        Registries {
+            default,
+            registries: HashMap::new(),
+            fallback: Some(source),
+        }
+    }
+
+    fn resolve_one(
+        http: &HttpClient,
+        protocol: Protocol,
+        config: &CargoConfig,
+        name: &str,
+        offline: bool,
+    ) -> Result<Arc<dyn RegistrySource>, TomieError> {
+        let resolved = config.resolve(name)?;
+        log::debug!(
+            "Registry {} resolves to {} at {:?}",
+            name,
+            resolved.name,
+            resolved.index
+        );
+
+        if offline {
+            let prefixes = match &resolved.index {
+                IndexLocation::CratesIo => {
+                    vec!["index.crates.io-".to_string(), "github.com-".to_string()]
+                }
+                IndexLocation::Sparse(url) | IndexLocation::Git(url) => {
+                    vec![format!("{}-", url_host(url))]
+                }
+            };
+            return Ok(Arc::new(LocalIndex::discover(&prefixes)?));
+        }
+
+        let http = http.for_source();
+        match &resolved.index {
+            IndexLocation::CratesIo => Ok(match protocol {
+                Protocol::Sparse => Arc::new(SparseSource::new(
+                    http,
+                    CRATES_IO_SPARSE_INDEX.to_string(),
+                    None,
+                )),
+                Protocol::Api => Arc::new(ApiSource::new(http, CRATES_IO_API.to_string())),
+            }),
+            IndexLocation::Sparse(url) => {
+                let token = resolved.token.clone().filter(|_| {
+                    let secure = !url.starts_with("http://");
+                    if !secure {
+                        log::warn!("Not sending the token of {} over plain HTTP", resolved.name);
+                    }
+                    secure
+                });
+                Ok(Arc::new(SparseSource::new(http, url.clone(), token)))
+            }
+            IndexLocation::Git(url) => Err(TomieError::RegistryConfig {
+                name: name.to_string(),
+                reason: format!(
+                    "git index {} can only be read offline; use a sparse+ index or --offline",
+                    url
+                ),
+            }),
+        }
+    }
+
+    /// The registry for a dependency's `registry` key, the default one when absent.
+    pub fn get(&self, name: Option<&str>) -> Result<&dyn RegistrySource, TomieError> {
+        let name = name.unwrap_or(&self.default);
+        match self.registries.get(name) {
+            Some(Ok(source)) => Ok(source.as_ref()),
+            None if self.fallback.is_some() => Ok(self.fallback.as_deref().unwrap()),
+            Some(Err(reason)) => Err(TomieError::RegistryConfig {
+                name: name.to_string(),
+                reason: reason.clone(),
+            }),
+            None => Err(TomieError::RegistryConfig {
+                name: name.to_string(),
+                reason: "unknown registry".to_string(),
+            }),
+        }
+    }
+
+    /// Resolved name of a dependency's registry.
+    pub fn name<'a>(&'a self, name: Option<&'a str>) -> &'a str {
+        name.unwrap_or(&self.default)
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &dyn RegistrySource> {
+        self.registries
+            .values()
+            .flatten()
+            .chain(&self.fallback)
+            .map(|source| source.as_ref())
+    }
+
+    /// Number of lookups so far that needed to be retried, every registry included.
+    pub fn retried_lookups(&self) -> usize {
+        self.sources().map(|source| source.retried_lookups()).sum()
+    }
+
+    /// Age of the oldest possibly stale data used so far, e.g. from the local index.
+    pub fn data_age(&self) -> Option<Duration> {
+        self.sources().filter_map(|source| source.data_age()).max()
+    }
+}
+
+fn url_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    without_scheme
+        .split(['/', ':'])
+        .next()
+        .unwrap_or(without_scheme)
+}
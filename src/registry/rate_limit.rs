@@ -0,0 +1,141 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex;
+
+/// Token bucket shared by every registry request of a run.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Set when the registry asked us to back off; nobody sends before it.
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Allows `per_second` requests on average, with bursts of up to `burst` requests.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        RateLimiter {
+            capacity,
+            per_second,
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent. A pause holds requests back even when
+    /// there is no rate limit.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.state.lock().await;
+                let now = Instant::now();
+
+                match bucket.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ if self.per_second <= 0.0 => return,
+                    _ => {
+                        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+                        bucket.tokens =
+                            (bucket.tokens + elapsed * self.per_second).min(self.capacity);
+                        bucket.refilled_at = now;
+
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second)
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds every request back for `delay`, e.g. after a 429 response.
+    pub async fn pause(&self, delay: Duration) {
+        let mut bucket = self.state.lock().await;
+        let until = Instant::now() + delay;
+        if bucket.paused_until.is_none_or(|current| current < until) {
+            bucket.paused_until = Some(until);
+        }
+        bucket.tokens = 0.0;
+    }
+}
+
+/// Reads a `Retry-After` header given either in seconds or as an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pauses_hold_back_unlimited_requests() {
+        let limiter = RateLimiter::new(0.0, 1);
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        limiter.pause(Duration::from_millis(150)).await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn bursts_then_the_average_rate() {
+        let limiter = RateLimiter::new(10.0, 2);
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // The bucket is empty: the next token takes a tenth of a second.
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn retry_after_in_seconds_or_as_a_date() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, " 7 ".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        let later = SystemTime::now() + Duration::from_secs(120);
+        headers.insert(RETRY_AFTER, httpdate::fmt_http_date(later).parse().unwrap());
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(110) && delay <= Duration::from_secs(120));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Status codes worth trying again by default; 429 is always retried after `Retry-After`.
+pub const DEFAULT_RETRY_STATUSES: [u16; 5] = [408, 500, 502, 503, 504];
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per lookup, the first one included.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub retry_statuses: Vec<u16>,
+}
+
+impl RetryPolicy {
+    pub fn is_transient_status(&self, status: reqwest::StatusCode) -> bool {
+        self.retry_statuses.contains(&status.as_u16())
+    }
+
+    /// Timeouts and connections failing or reset, before or while reading the body;
+    /// bodies are read as raw bytes, so a decode error there is a cut connection.
+    pub fn is_transient_error(&self, error: &reqwest::Error) -> bool {
+        error.is_timeout()
+            || error.is_connect()
+            || error.is_request()
+            || error.is_body()
+            || error.is_decode()
+    }
+
+    /// Exponential backoff with jitter: a random delay in the upper half of `base * 2^(attempt - 1)`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let half = ceiling / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// A random number in `[0, 1)`, good enough to spread retries apart.
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Lookups of a run that needed more than one attempt.
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    retried: AtomicUsize,
+}
+
+impl RetryStats {
+    pub fn record_retried(&self) {
+        self.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn retried(&self) -> usize {
+        self.retried.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            retry_statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = policy();
+        for (attempt, ceiling) in [(1, 100), (2, 200), (3, 400), (5, 1000), (40, 1000)] {
+            let ceiling = Duration::from_millis(ceiling);
+            let delay = policy.backoff(attempt);
+            assert!(
+                delay >= ceiling / 2 && delay <= ceiling,
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn transient_statuses() {
+        let policy = policy();
+        assert!(policy.is_transient_status(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.is_transient_status(reqwest::StatusCode::REQUEST_TIMEOUT));
+        assert!(!policy.is_transient_status(reqwest::StatusCode::NOT_FOUND));
+        assert!(!policy.is_transient_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[tokio::test]
+    async fn transient_errors() {
+        let policy = policy();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(200))
+            .no_proxy()
+            .build()
+            .unwrap();
+
+        let invalid = client.get("http://").send().await.unwrap_err();
+        assert!(!policy.is_transient_error(&invalid));
+
+        // Nothing listens on a port the listener was just dropped from.
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", closed.local_addr().unwrap());
+        drop(closed);
+        let refused = client.get(&url).send().await.unwrap_err();
+        assert!(policy.is_transient_error(&refused));
+
+        // Headers promising more body than is sent before the connection closes.
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = server.accept().await.unwrap();
+            let response = "HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n{\"vers\"";
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        let response = client.get(&url).send().await.unwrap();
+        let cut = response.bytes().await.unwrap_err();
+        assert!(policy.is_transient_error(&cut));
+
+        // A server that never answers.
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", silent.local_addr().unwrap());
+        let timeout = client.get(&url).send().await.unwrap_err();
+        assert!(timeout.is_timeout());
+        assert!(policy.is_transient_error(&timeout));
+    }
+}
@@ -0,0 +1,84 @@
+use super::http::HttpClient;
+use super::index::{index_path, parse_lines};
+use super::RegistrySource;
+use crate::error::TomieError;
+use crate::utils::CrateVersion;
+use futures::future::BoxFuture;
+use tokio::sync::OnceCell;
+
+/// A registry served through the sparse protocol, `{base}/{prefix}/{name}` as
+/// newline-delimited JSON.
+#[derive(Debug)]
+pub struct SparseSource {
+    http: HttpClient,
+    base_url: String,
+    /// Only sent once the registry's `config.json` says `auth-required`, as cargo does.
+    token: Option<String>,
+    client: OnceCell<HttpClient>,
+}
+
+impl SparseSource {
+    pub fn new(http: HttpClient, base_url: String, token: Option<String>) -> Self {
+        SparseSource {
+            http,
+            base_url,
+            token,
+            client: OnceCell::new(),
+        }
+    }
+
+    /// The client lookups go through, authenticated when the registry requires it.
+    async fn client(&self) -> &HttpClient {
+        let Some(token) = &self.token else {
+            return &self.http;
+        };
+        self.client
+            .get_or_init(|| async {
+                if self.auth_required().await {
+                    self.http.with_token(token.clone())
+                } else {
+                    self.http.clone()
+                }
+            })
+            .await
+    }
+
+    /// Whether the registry's `config.json` asks for authentication, which it
+    /// may also do by refusing to serve `config.json` itself.
+    async fn auth_required(&self) -> bool {
+        let url = format!("{}/config.json", self.base_url);
+        match self.http.get_json("config.json", &url).await {
+            Ok(config) => config["auth-required"].as_bool().unwrap_or(false),
+            Err(TomieError::HttpStatus { status, .. })
+                if status == reqwest::StatusCode::UNAUTHORIZED =>
+            {
+                true
+            }
+            Err(e) => {
+                log::debug!("Unable to read {}: {}", url, e);
+                false
+            }
+        }
+    }
+}
+
+impl RegistrySource for SparseSource {
+    fn list_versions<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<CrateVersion>, TomieError>> {
+        Box::pin(async move {
+            let url = format!("{}/{}", self.base_url, index_path(name));
+            self.client()
+                .await
+                .fetch_versions(&self.base_url, name, &url, |name, body| {
+                    Ok(parse_lines(name, body.split(|b| *b == b'\n')))
+                })
+                .await
+        })
+    }
+
+    fn retried_lookups(&self) -> usize {
+        self.http.retried_lookups()
+    }
+}
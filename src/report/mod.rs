@@ -0,0 +1,453 @@
+use crate::changelog::Changelog;
+use crate::utils::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Bumped whenever a field of the JSON report is renamed or removed.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct JsonReport<'a> {
+    pub schema_version: u32,
+    pub manifest_path: String,
+    pub dependencies: Vec<&'a DependencyAnalysis>,
+    pub git_dependencies: Vec<&'a GitAnalysis>,
+    pub path_mismatches: Vec<&'a PathMismatch>,
+    pub failures: Vec<&'a DependencyFailure>,
+    pub lookups: LookupStats,
+    pub local_index_age: Option<u64>,
+    /// Only filled in by `explain`.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub changelogs: &'a [Changelog],
+}
+
+impl<'a> JsonReport<'a> {
+    fn new(manifest_path: &Path, report: &'a AnalysisReport, only: Option<&str>) -> JsonReport<'a> {
+        JsonReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            manifest_path: manifest_path.display().to_string(),
+            dependencies: report
+                .dependencies
+                .iter()
+                .filter(|a| only.is_none_or(|name| a.is_named(name)))
+                .collect(),
+            git_dependencies: report
+                .git_dependencies
+                .iter()
+                .filter(|a| only.is_none_or(|name| a.is_named(name)))
+                .collect(),
+            path_mismatches: report
+                .path_mismatches
+                .iter()
+                .filter(|m| only.is_none_or(|name| m.name == name))
+                .collect(),
+            failures: report
+                .failures
+                .iter()
+                .filter(|f| only.is_none_or(|name| f.is_named(name)))
+                .collect(),
+            lookups: report.lookups,
+            local_index_age: report.local_index_age,
+            changelogs: &[],
+        }
+    }
+}
+
+/// `alias (real-name)` for renamed dependencies, the name alone otherwise.
+fn display_name(name: &str, package: Option<&str>) -> String {
+    match package {
+        Some(package) => format!("{} ({})", name, package),
+        None => name.to_string(),
+    }
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=119 => format!("{} seconds", secs),
+        120..=7199 => format!("{} minutes", secs / 60),
+        7200..=172_799 => format!("{} hours", secs / 3600),
+        _ => format!("{} days", secs / 86_400),
+    }
+}
+
+fn print_failures(out: &mut impl Write, failures: &[&DependencyFailure]) -> io::Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nFailed to analyze:")?;
+    for failure in failures {
+        writeln!(
+            out,
+            "{} {} {} ({}): {}",
+            failure.member,
+            failure.section,
+            display_name(&failure.name, failure.package.as_deref()),
+            failure.current_version,
+            failure.reason
+        )?;
+    }
+    Ok(())
+}
+
+fn short_commit(commit: &str) -> &str {
+    commit.get(..10).unwrap_or(commit)
+}
+
+fn print_git_dependencies(out: &mut impl Write, analyses: &[&GitAnalysis]) -> io::Result<()> {
+    if analyses.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nGit dependencies:")?;
+    for analysis in analyses {
+        let position = match analysis.commits_behind {
+            Some(0) => format!("up to date with {}", analysis.target),
+            Some(behind) => format!("{} commits behind {}", behind, analysis.target),
+            None => format!(
+                "follows {} at {}",
+                analysis.target,
+                short_commit(&analysis.target_commit)
+            ),
+        };
+        writeln!(
+            out,
+            "{} {} {} ({}): {}",
+            analysis.member,
+            analysis.section,
+            display_name(&analysis.name, analysis.package.as_deref()),
+            analysis.git,
+            position
+        )?;
+        if let Some(version) = &analysis.registry_version {
+            writeln!(
+                out,
+                "  note: {} {} is published on the registry",
+                analysis.package.as_deref().unwrap_or(&analysis.name),
+                version
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn print_path_mismatches(out: &mut impl Write, mismatches: &[&PathMismatch]) -> io::Result<()> {
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nPath dependencies out of sync with the local crate:")?;
+    for mismatch in mismatches {
+        writeln!(
+            out,
+            "{} {} {} (path {}): requires {}, local crate is {}",
+            mismatch.member,
+            mismatch.section,
+            mismatch.name,
+            mismatch.path,
+            mismatch.requirement,
+            mismatch.local_version
+        )?;
+    }
+    Ok(())
+}
+
+fn print_action(out: &mut impl Write, analysis: &DependencyAnalysis) -> io::Result<()> {
+    match analysis.action {
+        UpdateAction::None => {}
+        UpdateAction::CargoUpdate => {
+            writeln!(out, "  action: cargo update -p {}", analysis.crate_name())?
+        }
+        UpdateAction::EditManifest => writeln!(
+            out,
+            "  action: edit Cargo.toml to require {}",
+            analysis.latest_version
+        )?,
+    }
+    Ok(())
+}
+
+pub fn print_text_report(report: &AnalysisReport) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "\nAnalysis :")?;
+    writeln!(out, "------------------------")?;
+
+    let lookups = &report.lookups;
+    writeln!(
+        out,
+        "Registry lookups: {} ({} retried, {} failed)",
+        lookups.total, lookups.retried, lookups.failed
+    )?;
+
+    if let Some(age) = report.local_index_age {
+        writeln!(
+            out,
+            "Offline: local registry index data is up to {} old",
+            format_age(age)
+        )?;
+    }
+
+    let failures: Vec<&DependencyFailure> = report.failures.iter().collect();
+    print_failures(&mut out, &failures)?;
+
+    let mismatches: Vec<&PathMismatch> = report.path_mismatches.iter().collect();
+    print_path_mismatches(&mut out, &mismatches)?;
+
+    let git: Vec<&GitAnalysis> = report.git_dependencies.iter().collect();
+    print_git_dependencies(&mut out, &git)?;
+
+    let analyses = &report.dependencies;
+    if analyses.is_empty() {
+        if git.is_empty() && mismatches.is_empty() {
+            writeln!(out, "No dependencies analyzed successfully.")?;
+        }
+        return Ok(());
+    }
+
+    let mut by_section: BTreeMap<(&str, &DependencySection), Vec<&DependencyAnalysis>> =
+        BTreeMap::new();
+    for analysis in analyses {
+        by_section
+            .entry((analysis.member.as_str(), &analysis.section))
+            .or_default()
+            .push(analysis);
+    }
+
+    for ((member, section), analyses) in by_section {
+        writeln!(out, "\n{} {}", member, section)?;
+        for analysis in analyses {
+            let locked = match &analysis.locked_version {
+                Some(locked) => format!("locked {}, ", locked),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "{}: {} -> {}compatible {}, latest {} ({})",
+                display_name(&analysis.name, analysis.package.as_deref()),
+                analysis.current_version,
+                locked,
+                analysis.compatible_version.as_deref().unwrap_or("none"),
+                analysis.latest_version,
+                analysis.status
+            )?;
+            print_action(&mut out, analysis)?;
+            if analysis.pinned_yanked {
+                writeln!(
+                    out,
+                    "  note: the pinned version {} has been yanked",
+                    analysis.current_version
+                )?;
+            }
+        }
+    }
+
+    let members: BTreeSet<&str> = analyses.iter().map(|a| a.member.as_str()).collect();
+    if members.len() < 2 {
+        return Ok(());
+    }
+
+    let mut outdated: BTreeMap<(&str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for analysis in analyses.iter().filter(|a| a.is_outdated()) {
+        outdated
+            .entry((analysis.crate_name(), analysis.latest_version.as_str()))
+            .or_default()
+            .insert(analysis.member.as_str());
+    }
+
+    writeln!(out, "\nOutdated crates across the workspace:")?;
+    if outdated.is_empty() {
+        writeln!(out, "None.")?;
+    }
+    for ((name, latest), members) in outdated {
+        let members: Vec<&str> = members.into_iter().collect();
+        writeln!(
+            out,
+            "{} (latest {}) used by: {}",
+            name,
+            latest,
+            members.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+pub fn print_json_report(
+    manifest_path: &Path,
+    report: &AnalysisReport,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    let report = JsonReport::new(manifest_path, report, None);
+    writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+/// Prints every entry for the dependency `name`, across members and sections.
+pub fn print_explain(
+    manifest_path: &Path,
+    name: &str,
+    report: &AnalysisReport,
+    changelogs: &[Changelog],
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    let report = JsonReport {
+        changelogs,
+        ..JsonReport::new(manifest_path, report, Some(name))
+    };
+
+    if format == OutputFormat::Json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+
+    if report.dependencies.is_empty()
+        && report.git_dependencies.is_empty()
+        && report.path_mismatches.is_empty()
+        && report.failures.is_empty()
+    {
+        writeln!(out, "No analyzed dependency named {}.", name)?;
+        return Ok(());
+    }
+    print_failures(&mut out, &report.failures)?;
+    print_path_mismatches(&mut out, &report.path_mismatches)?;
+    for analysis in &report.git_dependencies {
+        writeln!(
+            out,
+            "{} in {} {}",
+            display_name(&analysis.name, analysis.package.as_deref()),
+            analysis.member,
+            analysis.section
+        )?;
+        writeln!(out, "  git:         {}", analysis.git)?;
+        writeln!(out, "  pinned:      {}", analysis.pinned_commit)?;
+        writeln!(
+            out,
+            "  {:<12} {}",
+            format!("{}:", analysis.target),
+            analysis.target_commit
+        )?;
+        if let Some(behind) = analysis.commits_behind {
+            writeln!(out, "  behind:      {} commits", behind)?;
+        }
+        if let Some(version) = &analysis.registry_version {
+            writeln!(out, "  registry:    {}", version)?;
+        }
+    }
+    for analysis in report.dependencies {
+        writeln!(
+            out,
+            "{} in {} {}",
+            display_name(&analysis.name, analysis.package.as_deref()),
+            analysis.member,
+            analysis.section
+        )?;
+        writeln!(out, "  requirement: {}", analysis.current_version)?;
+        if let Some(locked) = &analysis.locked_version {
+            writeln!(out, "  locked:      {}", locked)?;
+        }
+        writeln!(
+            out,
+            "  compatible:  {}",
+            analysis.compatible_version.as_deref().unwrap_or("none")
+        )?;
+        writeln!(out, "  latest:      {}", analysis.latest_version)?;
+        if let Some(rust_version) = &analysis.latest_rust_version {
+            writeln!(out, "  latest MSRV: {}", rust_version)?;
+        }
+        writeln!(out, "  status:      {}", analysis.status)?;
+        if analysis.pinned_yanked {
+            writeln!(out, "  note:        the pinned version has been yanked")?;
+        }
+        if let Some(changelog) = changelog_of(changelogs, analysis) {
+            print_changelog(&mut out, changelog)?;
+        }
+    }
+    Ok(())
+}
+
+/// The changelog from the version `analysis` is on to its latest release; members may
+/// use the crate at different versions, each with its own changelog.
+fn changelog_of<'a>(
+    changelogs: &'a [Changelog],
+    analysis: &DependencyAnalysis,
+) -> Option<&'a Changelog> {
+    let from = analysis.version_in_use()?.to_string();
+    changelogs.iter().find(|c| {
+        c.name == analysis.crate_name() && c.from == from && c.to == analysis.latest_version
+    })
+}
+
+/// The changelog sections, indented under the dependency they belong to.
+fn print_changelog(out: &mut impl Write, changelog: &Changelog) -> io::Result<()> {
+    writeln!(
+        out,
+        "  changelog:   {} in {} ({} -> {})",
+        changelog.file, changelog.repository, changelog.from, changelog.to
+    )?;
+    if changelog.sections.is_empty() {
+        writeln!(out, "    No release in between is listed.")?;
+    }
+    for section in &changelog.sections {
+        writeln!(out, "    {}", section.version)?;
+        for line in section.text.lines() {
+            if line.trim().is_empty() {
+                writeln!(out)?;
+            } else {
+                writeln!(out, "      {}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn analysis(member: &str, locked: &str) -> DependencyAnalysis {
+        DependencyAnalysis {
+            name: "serde".to_string(),
+            package: None,
+            member: member.to_string(),
+            manifest_path: PathBuf::from(format!("{}/Cargo.toml", member)),
+            from_workspace: false,
+            section: DependencySection {
+                kind: DependencyKind::Normal,
+                target: None,
+            },
+            current_version: "1.0".to_string(),
+            registry: None,
+            locked_version: Some(locked.to_string()),
+            compatible_version: Some("1.0.200".to_string()),
+            latest_version: "1.0.200".to_string(),
+            status: UpdateStatus::CompatibleUpdate,
+            action: UpdateAction::CargoUpdate,
+            pinned_yanked: false,
+            latest_rust_version: None,
+            candidates: Vec::new(),
+        }
+    }
+
+    fn changelog(from: &str) -> Changelog {
+        Changelog {
+            name: "serde".to_string(),
+            repository: "https://github.com/serde-rs/serde".to_string(),
+            file: "CHANGELOG.md".to_string(),
+            from: from.to_string(),
+            to: "1.0.200".to_string(),
+            sections: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn each_member_gets_the_changelog_from_its_version() {
+        let changelogs = [changelog("1.0.100"), changelog("1.0.150")];
+        let from = |member, locked| {
+            changelog_of(&changelogs, &analysis(member, locked)).map(|c| c.from.as_str())
+        };
+        assert_eq!(from("app", "1.0.150"), Some("1.0.150"));
+        assert_eq!(from("lib", "1.0.100"), Some("1.0.100"));
+        assert_eq!(from("cli", "1.0.120"), None);
+    }
+}
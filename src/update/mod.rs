@@ -0,0 +1,695 @@
+use crate::error::TomieError;
+use crate::utils::*;
+use semver::{Op, Version, VersionReq};
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// A new version requirement proposed for one dependency entry.
+#[derive(Debug, Serialize)]
+pub struct ProposedUpdate {
+    pub name: String,
+    pub member: String,
+    pub section: DependencySection,
+    /// Manifest of the member; the requirement itself lives in the workspace root
+    /// manifest when `from_workspace`.
+    pub manifest_path: PathBuf,
+    pub from_workspace: bool,
+    pub from: String,
+    pub to: String,
+    pub status: UpdateStatus,
+}
+
+/// `from` moved to `version`, keeping its operator and precision, e.g. `=1.2.0` to
+/// `=1.4.1` or `0.3` to `1.2`. Requirements made of several comparators are left to a human.
+fn new_requirement(from: &str, version: &str) -> Option<String> {
+    let req = VersionReq::parse(from).ok()?;
+    let [comparator] = req.comparators.as_slice() else {
+        return None;
+    };
+    let mut version = Version::parse(version).ok()?;
+    // Build metadata is meaningless, and rejected, in a requirement.
+    version.build = semver::BuildMetadata::EMPTY;
+
+    let op = match comparator.op {
+        // A bare `1.2` is a caret requirement too; keep it bare.
+        Op::Caret if !from.trim_start().starts_with('^') => "",
+        Op::Caret => "^",
+        Op::Exact => "=",
+        Op::Tilde => "~",
+        Op::GreaterEq => ">=",
+        _ => return None,
+    };
+    let version = match (comparator.minor, comparator.patch) {
+        _ if !version.pre.is_empty() => version.to_string(),
+        (None, _) => version.major.to_string(),
+        (Some(_), None) => format!("{}.{}", version.major, version.minor),
+        (Some(_), Some(_)) => version.to_string(),
+    };
+    Some(format!("{}{}", op, version))
+}
+
+/// Whether `new_requirement` can move `requirement` to another version.
+pub fn is_rewritable(requirement: &str) -> bool {
+    VersionReq::parse(requirement).is_ok_and(|req| {
+        matches!(req.comparators.as_slice(), [comparator]
+            if matches!(comparator.op, Op::Caret | Op::Exact | Op::Tilde | Op::GreaterEq))
+    })
+}
+
+/// Whether moving to `version` stays within what `requirement` already accepts.
+fn update_status(requirement: &str, version: &str) -> UpdateStatus {
+    let accepted = VersionReq::parse(requirement)
+        .ok()
+        .zip(Version::parse(version).ok())
+        .is_some_and(|(req, version)| req.matches(&version));
+    if accepted {
+        UpdateStatus::CompatibleUpdate
+    } else {
+        UpdateStatus::BreakingUpdate
+    }
+}
+
+impl ProposedUpdate {
+    /// Moving the requirement of `analysis` to `version`; `None` when that changes
+    /// nothing, would be a downgrade, or the requirement is too complex to rewrite.
+    pub fn new(analysis: &DependencyAnalysis, version: &str) -> Option<Self> {
+        let floor = VersionReq::parse(&analysis.current_version)
+            .ok()
+            .and_then(|req| requirement_floor(&req));
+        if floor
+            .zip(Version::parse(version).ok())
+            .is_some_and(|(floor, version)| version < floor)
+        {
+            log::warn!(
+                "Not rewriting {} {}: {} is older than the requirement",
+                analysis.name,
+                analysis.current_version,
+                version
+            );
+            return None;
+        }
+        let Some(to) = new_requirement(&analysis.current_version, version) else {
+            log::warn!(
+                "Not rewriting {} {}: only single-comparator requirements are updated",
+                analysis.name,
+                analysis.current_version
+            );
+            return None;
+        };
+        if to == analysis.current_version {
+            return None;
+        }
+        Some(ProposedUpdate {
+            name: analysis.name.clone(),
+            member: analysis.member.clone(),
+            section: analysis.section.clone(),
+            manifest_path: analysis.manifest_path.clone(),
+            from_workspace: analysis.from_workspace,
+            status: update_status(&analysis.current_version, version),
+            from: analysis.current_version.clone(),
+            to,
+        })
+    }
+
+    /// Member and table the requirement is written in, for display.
+    fn location(&self) -> (&str, String) {
+        if self.from_workspace {
+            ("workspace", "[workspace.dependencies]".to_string())
+        } else {
+            (&self.member, self.section.to_string())
+        }
+    }
+}
+
+/// Picks the target version of every outdated dependency, limited to `crates` when not empty.
+/// An entry of `[workspace.dependencies]` is proposed once, however many members inherit it.
+pub fn propose_updates(
+    analyses: &[DependencyAnalysis],
+    crates: &[String],
+    breaking: bool,
+) -> Vec<ProposedUpdate> {
+    let mut inherited = BTreeSet::new();
+    analyses
+        .iter()
+        .filter(|a| crates.is_empty() || crates.iter().any(|c| a.is_named(c)))
+        .filter(|a| !a.from_workspace || inherited.insert(a.name.as_str()))
+        .filter_map(|a| {
+            let version = match a.status {
+                UpdateStatus::UpToDate => return None,
+                UpdateStatus::CompatibleUpdate => &a.latest_version,
+                UpdateStatus::BreakingUpdate if breaking => &a.latest_version,
+                UpdateStatus::BreakingUpdate => a.compatible_version.as_ref()?,
+            };
+            ProposedUpdate::new(a, version)
+        })
+        .collect()
+}
+
+/// A manifest before and after its requirements were rewritten.
+#[derive(Debug)]
+pub struct ManifestEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub updated: String,
+}
+
+impl ManifestEdit {
+    /// Unified diff of the edit, with `display_path` in git-style `a/` and `b/` headers.
+    pub fn unified_diff(&self, display_path: &str) -> String {
+        TextDiff::from_lines(&self.original, &self.updated)
+            .unified_diff()
+            .context_radius(3)
+            .header(
+                &format!("a/{}", display_path),
+                &format!("b/{}", display_path),
+            )
+            .to_string()
+    }
+
+    /// Replaces the manifest on disk, through a rename so it is never half written.
+    pub fn write(&self) -> Result<(), TomieError> {
+        let partial = self.path.with_extension("toml.partial");
+        fs::write(&partial, &self.updated)
+            .and_then(|()| fs::rename(&partial, &self.path))
+            .map_err(|source| TomieError::ManifestIo {
+                path: self.path.clone(),
+                source,
+            })
+    }
+}
+
+/// Replaces a requirement string, keeping its quotes and the whitespace and comments around it.
+fn replace_requirement(value: &mut Value, requirement: &str) -> bool {
+    let Value::String(current) = value else {
+        return false;
+    };
+    let literal = current
+        .as_repr()
+        .and_then(|repr| repr.as_raw().as_str())
+        .is_some_and(|raw| raw.starts_with('\''));
+    let decor = value.decor().clone();
+    *value = match format!("'{}'", requirement).parse() {
+        Ok(quoted) if literal => quoted,
+        _ => Value::from(requirement),
+    };
+    *value.decor_mut() = decor;
+    true
+}
+
+/// Sets the requirement of a `dep = "1"`, `dep = { version = "1" }` or
+/// `[dependencies.dep]` entry.
+fn set_requirement(item: &mut Item, requirement: &str) -> bool {
+    if let Some(table) = item.as_table_like_mut() {
+        return match table.get_mut("version").and_then(Item::as_value_mut) {
+            Some(value) => replace_requirement(value, requirement),
+            None => false,
+        };
+    }
+    match item.as_value_mut() {
+        Some(value) => replace_requirement(value, requirement),
+        None => false,
+    }
+}
+
+/// The dependency table holding the requirement an update changes.
+fn dependency_table<'a>(
+    doc: &'a mut DocumentMut,
+    update: &ProposedUpdate,
+) -> Option<&'a mut dyn toml_edit::TableLike> {
+    if update.from_workspace {
+        return doc
+            .get_mut("workspace")?
+            .get_mut("dependencies")?
+            .as_table_like_mut();
+    }
+    let item = match &update.section.target {
+        Some(target) => doc.get_mut("target")?.get_mut(target)?,
+        None => doc.as_item_mut(),
+    };
+    let kind = update.section.kind;
+    // Cargo still accepts the underscore spelling of the dev and build tables.
+    let underscored = kind.table_name().replace('-', "_");
+    let key = if item.get(kind.table_name()).is_none() && item.get(&underscored).is_some() {
+        underscored.as_str()
+    } else {
+        kind.table_name()
+    };
+    item.get_mut(key)?.as_table_like_mut()
+}
+
+/// Rewrites the requirements of `updates` in their manifests; those inherited from
+/// the workspace are changed once, in `root_manifest`.
+pub fn edit_manifests(
+    updates: &[ProposedUpdate],
+    root_manifest: &Path,
+) -> Result<Vec<ManifestEdit>, TomieError> {
+    let mut by_manifest: BTreeMap<&Path, Vec<&ProposedUpdate>> = BTreeMap::new();
+    let mut inherited: BTreeMap<&str, &str> = BTreeMap::new();
+    for update in updates {
+        let path = if update.from_workspace {
+            // One entry of the root manifest, whichever members inherit it.
+            if let Some(kept) = inherited.get(update.name.as_str()) {
+                if *kept != update.to {
+                    log::warn!(
+                        "{} is inherited from the workspace; keeping {} over {}",
+                        update.name,
+                        kept,
+                        update.to
+                    );
+                }
+                continue;
+            }
+            inherited.insert(&update.name, &update.to);
+            root_manifest
+        } else {
+            update.manifest_path.as_path()
+        };
+        by_manifest.entry(path).or_default().push(update);
+    }
+
+    let mut edits = Vec::new();
+    for (path, updates) in by_manifest {
+        let original = fs::read_to_string(path).map_err(|source| TomieError::ManifestIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut doc: DocumentMut = original.parse().map_err(|source| TomieError::TomlEdit {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        for update in updates {
+            let found = dependency_table(&mut doc, update)
+                .and_then(|table| table.get_mut(&update.name))
+                .is_some_and(|item| set_requirement(item, &update.to));
+            if !found {
+                log::warn!(
+                    "Unable to find the requirement of {} in {}",
+                    update.name,
+                    path.display()
+                );
+            }
+        }
+
+        let updated = doc.to_string();
+        if updated != original {
+            edits.push(ManifestEdit {
+                path: path.to_path_buf(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+pub fn print_updates(
+    updates: &[ProposedUpdate],
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    if format == OutputFormat::Json {
+        writeln!(out, "{}", serde_json::to_string_pretty(updates)?)?;
+        return Ok(());
+    }
+
+    if updates.is_empty() {
+        writeln!(out, "Nothing to update.")?;
+        return Ok(());
+    }
+    for update in updates {
+        let (member, section) = update.location();
+        writeln!(
+            out,
+            "{} {} {}: {} -> {} ({})",
+            member, section, update.name, update.from, update.to, update.status
+        )?;
+    }
+    Ok(())
+}
+
+/// `path` relative to `root_dir` when below it, for diff headers.
+fn display_path(path: &Path, root_dir: &Path) -> String {
+    path.strip_prefix(root_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+pub fn print_diffs(edits: &[ManifestEdit], root_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    if edits.is_empty() {
+        writeln!(out, "Nothing to update.")?;
+    }
+    for edit in edits {
+        write!(
+            out,
+            "{}",
+            edit.unified_diff(&display_path(&edit.path, root_dir))
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct JsonDiff<'a> {
+    updates: &'a [ProposedUpdate],
+    diffs: Vec<JsonManifestDiff>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonManifestDiff {
+    path: String,
+    diff: String,
+}
+
+/// Prints the proposed updates and the diff of every affected manifest, ready to be
+/// pasted in a review; `root_dir` is what manifest paths are shown relative to.
+pub fn print_review(
+    updates: &[ProposedUpdate],
+    edits: &[ManifestEdit],
+    root_dir: &Path,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut out = io::stdout().lock();
+    if format == OutputFormat::Json {
+        let diffs = edits
+            .iter()
+            .map(|edit| {
+                let path = display_path(&edit.path, root_dir);
+                JsonManifestDiff {
+                    diff: edit.unified_diff(&path),
+                    path,
+                }
+            })
+            .collect();
+        let report = JsonDiff { updates, diffs };
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+        return Ok(());
+    }
+
+    if edits.is_empty() {
+        writeln!(out, "No dependency requirement needs updating.")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "### Dependency updates ({} in {} manifest{})\n",
+        updates.len(),
+        edits.len(),
+        if edits.len() == 1 { "" } else { "s" }
+    )?;
+    writeln!(out, "| Crate | Member | Section | From | To | Status |")?;
+    writeln!(out, "|---|---|---|---|---|---|")?;
+    for update in updates {
+        let (member, section) = update.location();
+        writeln!(
+            out,
+            "| `{}` | {} | `{}` | `{}` | `{}` | {} |",
+            update.name, member, section, update.from, update.to, update.status
+        )?;
+    }
+    writeln!(out, "\n```diff")?;
+    for edit in edits {
+        write!(
+            out,
+            "{}",
+            edit.unified_diff(&display_path(&edit.path, root_dir))
+        )?;
+    }
+    writeln!(out, "```")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(
+        manifest_path: &Path,
+        name: &str,
+        section: DependencySection,
+        from_workspace: bool,
+        to: &str,
+    ) -> ProposedUpdate {
+        ProposedUpdate {
+            name: name.to_string(),
+            member: "demo".to_string(),
+            section,
+            manifest_path: manifest_path.to_path_buf(),
+            from_workspace,
+            from: String::new(),
+            to: to.to_string(),
+            status: UpdateStatus::BreakingUpdate,
+        }
+    }
+
+    fn section(kind: DependencyKind, target: Option<&str>) -> DependencySection {
+        DependencySection {
+            kind,
+            target: target.map(String::from),
+        }
+    }
+
+    fn analysis(current: &str, latest: &str, status: UpdateStatus) -> DependencyAnalysis {
+        DependencyAnalysis {
+            name: "foo".to_string(),
+            package: None,
+            member: "demo".to_string(),
+            manifest_path: PathBuf::from("Cargo.toml"),
+            from_workspace: false,
+            section: section(DependencyKind::Normal, None),
+            current_version: current.to_string(),
+            registry: None,
+            locked_version: None,
+            compatible_version: None,
+            latest_version: latest.to_string(),
+            status,
+            action: UpdateAction::EditManifest,
+            pinned_yanked: false,
+            latest_rust_version: None,
+            candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn new_requirement_keeps_operator_and_precision() {
+        assert_eq!(new_requirement("1.2.3", "1.4.1").as_deref(), Some("1.4.1"));
+        assert_eq!(new_requirement("^1.2", "2.0.3").as_deref(), Some("^2.0"));
+        assert_eq!(
+            new_requirement("=1.2.0", "1.4.1").as_deref(),
+            Some("=1.4.1")
+        );
+        assert_eq!(
+            new_requirement("~0.3.1", "0.3.7").as_deref(),
+            Some("~0.3.7")
+        );
+        assert_eq!(new_requirement(">=0.5", "0.6.2").as_deref(), Some(">=0.6"));
+        assert_eq!(new_requirement("6", "7.0.1").as_deref(), Some("7"));
+        assert_eq!(
+            new_requirement("1.0", "2.0.0-rc.1").as_deref(),
+            Some("2.0.0-rc.1")
+        );
+        assert_eq!(new_requirement("1", "1.2.0+build.5").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn new_requirement_leaves_complex_requirements_alone() {
+        assert_eq!(new_requirement(">=1.2, <2", "2.1.0"), None);
+        assert_eq!(new_requirement("<2", "2.1.0"), None);
+        assert_eq!(new_requirement("1.*", "2.1.0"), None);
+        assert!(!is_rewritable(">=1.2, <2"));
+        assert!(is_rewritable("~1.2"));
+    }
+
+    #[test]
+    fn update_status_follows_the_old_requirement() {
+        assert_eq!(
+            update_status("~0.3.1", "0.3.7"),
+            UpdateStatus::CompatibleUpdate
+        );
+        assert_eq!(
+            update_status("~0.3.1", "0.4.0"),
+            UpdateStatus::BreakingUpdate
+        );
+    }
+
+    #[test]
+    fn propose_updates_never_downgrades() {
+        let analyses = [
+            analysis("2.0.0-rc.1", "1.5.0", UpdateStatus::BreakingUpdate),
+            analysis("=1.0.0", "0.9.0", UpdateStatus::BreakingUpdate),
+            analysis("0.3", "1.0.0", UpdateStatus::BreakingUpdate),
+        ];
+        let updates = propose_updates(&analyses, &[], true);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            (updates[0].from.as_str(), updates[0].to.as_str()),
+            ("0.3", "1.0")
+        );
+        assert!(ProposedUpdate::new(&analyses[0], "1.5.0").is_none());
+    }
+
+    #[test]
+    fn propose_updates_once_per_inherited_entry() {
+        let inherited = |member: &str| DependencyAnalysis {
+            member: member.to_string(),
+            from_workspace: true,
+            ..analysis("1.0", "2.0.0", UpdateStatus::BreakingUpdate)
+        };
+        let analyses = [
+            inherited("app"),
+            inherited("lib"),
+            DependencyAnalysis {
+                member: "lib".to_string(),
+                section: section(DependencyKind::Dev, None),
+                ..analysis("1.0", "2.0.0", UpdateStatus::BreakingUpdate)
+            },
+        ];
+        let updates = propose_updates(&analyses, &[], true);
+        let rows: Vec<(&str, bool)> = updates
+            .iter()
+            .map(|u| (u.member.as_str(), u.from_workspace))
+            .collect();
+        assert_eq!(rows, [("app", true), ("lib", false)]);
+    }
+
+    #[test]
+    fn edit_manifests_keeps_formatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        let original = r#"[package]
+name = "demo"
+
+[dependencies]
+# Serialization
+serde = "1.0"   # pinned by the API
+tokio = { version = '1.2', features = ["full"] }
+
+[dependencies.regex]
+version = "1.5"
+default-features = false
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[dev_dependencies]
+insta = "1"
+"#;
+        fs::write(&manifest, original).unwrap();
+        let normal = || section(DependencyKind::Normal, None);
+        let updates = [
+            update(&manifest, "serde", normal(), false, "1.2"),
+            update(&manifest, "tokio", normal(), false, "2.0"),
+            update(&manifest, "regex", normal(), false, "1.11"),
+            update(
+                &manifest,
+                "libc",
+                section(DependencyKind::Normal, Some("cfg(unix)")),
+                false,
+                "0.3",
+            ),
+            update(
+                &manifest,
+                "insta",
+                section(DependencyKind::Dev, None),
+                false,
+                "2",
+            ),
+        ];
+
+        let edits = edit_manifests(&updates, &manifest).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].updated,
+            r#"[package]
+name = "demo"
+
+[dependencies]
+# Serialization
+serde = "1.2"   # pinned by the API
+tokio = { version = '2.0', features = ["full"] }
+
+[dependencies.regex]
+version = "1.11"
+default-features = false
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.3"
+
+[dev_dependencies]
+insta = "2"
+"#
+        );
+        // Nothing is written until asked.
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), original);
+
+        edits[0].write().unwrap();
+        assert_eq!(fs::read_to_string(&manifest).unwrap(), edits[0].updated);
+    }
+
+    #[test]
+    fn edit_manifests_changes_inherited_requirements_in_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Cargo.toml");
+        let member = dir.path().join("app").join("Cargo.toml");
+        fs::create_dir(dir.path().join("app")).unwrap();
+        fs::write(
+            &root,
+            "[workspace]\nmembers = [\"app\"]\n\n[workspace.dependencies]\nserde = \"1.0\"\n",
+        )
+        .unwrap();
+        let member_manifest =
+            "[package]\nname = \"app\"\n\n[dependencies]\nserde.workspace = true\n";
+        fs::write(&member, member_manifest).unwrap();
+
+        let updates = [
+            update(
+                &member,
+                "serde",
+                section(DependencyKind::Normal, None),
+                true,
+                "1.2",
+            ),
+            update(
+                &member,
+                "serde",
+                section(DependencyKind::Dev, None),
+                true,
+                "1.3",
+            ),
+        ];
+        let edits = edit_manifests(&updates, &root).unwrap();
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].path, root);
+        assert!(edits[0]
+            .updated
+            .ends_with("[workspace.dependencies]\nserde = \"1.2\"\n"));
+        assert_eq!(
+            edits[0].unified_diff("Cargo.toml"),
+            "--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -2,4 +2,4 @@\n members = [\"app\"]\n \n [workspace.dependencies]\n-serde = \"1.0\"\n+serde = \"1.2\"\n"
+        );
+    }
+
+    #[test]
+    fn edit_manifests_skips_missing_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package]\nname = \"demo\"\n").unwrap();
+        let updates = [update(
+            &manifest,
+            "serde",
+            section(DependencyKind::Normal, None),
+            false,
+            "1.2",
+        )];
+        assert!(edit_manifests(&updates, &manifest).unwrap().is_empty());
+    }
+}
@@ -1,30 +1,741 @@
+use crate::error::TomieError;
+use semver::{Op, Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub type DependencyTable = BTreeMap<String, Dependency>;
 
 #[derive(Debug, Deserialize)]
 pub struct Tomie {
-    pub dependencies: Option<std::collections::BTreeMap<String, Dependency>>,
+    pub package: Option<Package>,
+    pub workspace: Option<Workspace>,
+    pub dependencies: Option<DependencyTable>,
+    #[serde(rename = "dev-dependencies", alias = "dev_dependencies")]
+    pub dev_dependencies: Option<DependencyTable>,
+    #[serde(rename = "build-dependencies", alias = "build_dependencies")]
+    pub build_dependencies: Option<DependencyTable>,
+    pub target: Option<BTreeMap<String, TargetTables>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: Option<PackageVersion>,
+}
+
+/// `version = "1.2.3"`, or `version.workspace = true` to take the workspace's.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PackageVersion {
+    Literal(String),
+    Inherited { workspace: bool },
+}
+
+/// Keys a member package can inherit from `[workspace.package]`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkspacePackage {
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
+pub struct Workspace {
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub package: Option<WorkspacePackage>,
+    pub dependencies: Option<DependencyTable>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TargetTables {
+    pub dependencies: Option<DependencyTable>,
+    #[serde(rename = "dev-dependencies", alias = "dev_dependencies")]
+    pub dev_dependencies: Option<DependencyTable>,
+    #[serde(rename = "build-dependencies", alias = "build_dependencies")]
+    pub build_dependencies: Option<DependencyTable>,
+}
+
+impl Tomie {
+    /// Every dependency table of the manifest, target-specific ones included.
+    pub fn into_sections(self) -> Vec<(DependencySection, DependencyTable)> {
+        let mut sections = Vec::new();
+        push_tables(
+            &mut sections,
+            None,
+            self.dependencies,
+            self.dev_dependencies,
+            self.build_dependencies,
+        );
+
+        for (cfg, tables) in self.target.unwrap_or_default() {
+            push_tables(
+                &mut sections,
+                Some(cfg),
+                tables.dependencies,
+                tables.dev_dependencies,
+                tables.build_dependencies,
+            );
+        }
+
+        sections
+    }
+}
+
+fn push_tables(
+    sections: &mut Vec<(DependencySection, DependencyTable)>,
+    target: Option<String>,
+    normal: Option<DependencyTable>,
+    dev: Option<DependencyTable>,
+    build: Option<DependencyTable>,
+) {
+    let tables = [
+        (DependencyKind::Normal, normal),
+        (DependencyKind::Dev, dev),
+        (DependencyKind::Build, build),
+    ];
+    for (kind, table) in tables {
+        if let Some(table) = table {
+            let section = DependencySection {
+                kind,
+                target: target.clone(),
+            };
+            sections.push((section, table));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum Dependency {
     Simple(String),
     Detailed(DependencyDetail),
 }
 
-#[derive(Debug, Deserialize)]
+impl Dependency {
+    /// Name of the registry the dependency comes from, when not the default one.
+    pub fn registry(&self) -> Option<&str> {
+        match self {
+            Dependency::Detailed(detail) => detail.registry.as_deref(),
+            Dependency::Simple(_) => None,
+        }
+    }
+
+    /// The crate's name on the registry, `package` when the entry is renamed.
+    pub fn package(&self) -> Option<&str> {
+        match self {
+            Dependency::Detailed(detail) => detail.package.as_deref(),
+            Dependency::Simple(_) => None,
+        }
+    }
+
+    /// The `version` requirement, whatever the form of the entry.
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            Dependency::Simple(version) => Some(version),
+            Dependency::Detailed(detail) => detail.version.as_deref(),
+        }
+    }
+
+    /// Directory of a `path = "..."` dependency, relative to the manifest declaring it.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Dependency::Detailed(detail) => detail.path.as_deref(),
+            Dependency::Simple(_) => None,
+        }
+    }
+
+    /// Repository and reference of a `git = "..."` dependency.
+    pub fn git_spec(&self) -> Option<GitSpec> {
+        let Dependency::Detailed(detail) = self else {
+            return None;
+        };
+        let url = detail.git.clone()?;
+        let (reference, branch) = match (&detail.branch, &detail.tag, &detail.rev) {
+            (branch, _, Some(rev)) => (GitReference::Rev(rev.clone()), branch.clone()),
+            (_, Some(tag), None) => (GitReference::Tag(tag.clone()), None),
+            (Some(branch), None, None) => (GitReference::Branch(branch.clone()), None),
+            (None, None, None) => (GitReference::DefaultBranch, None),
+        };
+        Some(GitSpec {
+            url,
+            reference,
+            branch,
+        })
+    }
+
+    /// Whether the entry is `dep = { workspace = true }`.
+    pub fn is_workspace_inherited(&self) -> bool {
+        matches!(self, Dependency::Detailed(detail) if detail.workspace == Some(true))
+    }
+
+    /// The `[workspace.dependencies]` entry, as taken by a member whose directory is
+    /// reached from the workspace root through `to_root`; a `path` is relative to the root.
+    pub fn inherited(&self, to_root: &Path) -> Dependency {
+        let mut detail = match self {
+            Dependency::Simple(version) => DependencyDetail {
+                version: Some(version.clone()),
+                ..Default::default()
+            },
+            Dependency::Detailed(detail) => detail.clone(),
+        };
+        if let Some(path) = &detail.path {
+            detail.path = Some(to_root.join(path).to_string_lossy().into_owned());
+        }
+        detail.from_workspace = true;
+        Dependency::Detailed(detail)
+    }
+
+    /// Whether the entry was taken from `[workspace.dependencies]`.
+    pub fn is_from_workspace(&self) -> bool {
+        matches!(self, Dependency::Detailed(detail) if detail.from_workspace)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct DependencyDetail {
     pub version: Option<String>,
+    pub workspace: Option<bool>,
+    pub registry: Option<String>,
+    pub path: Option<String>,
+    /// Real crate name when the table key is an alias.
+    pub package: Option<String>,
+    pub git: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+    /// Set once a `workspace = true` entry is replaced by the workspace's.
     #[serde(skip)]
-    pub _path: Option<String>,
-    #[serde(skip)]
-    pub _git: Option<String>,
+    pub from_workspace: bool,
+}
+
+/// Which commit of its repository a git dependency uses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "kind", content = "name")]
+pub enum GitReference {
+    DefaultBranch,
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl fmt::Display for GitReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitReference::DefaultBranch => f.write_str("default branch"),
+            GitReference::Branch(branch) => write!(f, "branch {}", branch),
+            GitReference::Tag(tag) => write!(f, "tag {}", tag),
+            GitReference::Rev(rev) => write!(f, "rev {}", rev),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitSpec {
+    pub url: String,
+    pub reference: GitReference,
+    /// Branch a `rev` was picked from, followed instead of the default branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl fmt::Display for GitSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.url, self.reference)?;
+        match &self.branch {
+            Some(branch) => write!(f, " on branch {}", branch),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+}
+
+impl DependencyKind {
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "dependencies",
+            DependencyKind::Dev => "dev-dependencies",
+            DependencyKind::Build => "build-dependencies",
+        }
+    }
+}
+
+/// The table a dependency was declared in, e.g. `[target.'cfg(unix)'.dev-dependencies]`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DependencySection {
+    pub kind: DependencyKind,
+    pub target: Option<String>,
+}
+
+impl fmt::Display for DependencySection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Some(target) => write!(f, "[target.'{}'.{}]", target, self.kind.table_name()),
+            None => write!(f, "[{}]", self.kind.table_name()),
+        }
+    }
+}
+
+/// A release of a crate as published on the registry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateVersion {
+    pub num: String,
+    pub yanked: bool,
+    /// Only known when the version comes from a registry index.
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    #[serde(default)]
+    pub features: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub dependencies: Vec<CrateVersionDependency>,
+}
+
+/// A dependency of a published crate version, as listed in the registry index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateVersionDependency {
+    pub name: String,
+    pub req: String,
+    pub kind: Option<String>,
+    pub optional: bool,
+    pub target: Option<String>,
+    /// Real crate name when the dependency is renamed.
+    pub package: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AnalysisOptions {
+    /// Consider `-alpha`/`-beta`/`-rc` releases when picking the newest version.
+    pub include_prerelease: bool,
+    /// Registry lookups in flight at the same time.
+    pub jobs: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// A dependency known by its key in the manifest, `name`, which aliases the real crate
+/// when the entry has a `package` key.
+pub trait DependencyName {
+    fn name(&self) -> &str;
+    fn package(&self) -> Option<&str>;
+
+    /// The real crate name, to look the crate up by on the registry and in `Cargo.lock`.
+    fn crate_name(&self) -> &str {
+        self.package().unwrap_or(self.name())
+    }
+
+    /// Whether `name` is the key of the dependency or its real crate name.
+    fn is_named(&self, name: &str) -> bool {
+        self.name() == name || self.package() == Some(name)
+    }
+}
+
+macro_rules! impl_dependency_name {
+    ($($ty:ty),*) => {$(
+        impl DependencyName for $ty {
+            fn name(&self) -> &str {
+                &self.name
+            }
+
+            fn package(&self) -> Option<&str> {
+                self.package.as_deref()
+            }
+        }
+    )*};
+}
+
+impl_dependency_name!(
+    DependencyRequest,
+    GitRequest,
+    GitAnalysis,
+    DependencyAnalysis,
+    DependencyFailure
+);
+
+/// A single dependency entry of a member manifest, waiting for its registry lookup.
+#[derive(Debug)]
+pub struct DependencyRequest {
+    pub name: String,
+    pub package: Option<String>,
+    pub member: String,
+    pub manifest_path: PathBuf,
+    /// The requirement is written in `[workspace.dependencies]` of the root manifest.
+    pub from_workspace: bool,
+    pub section: DependencySection,
+    pub current_version: String,
+    pub registry: Option<String>,
+}
+
+/// A `git = "..."` dependency of a member manifest.
+#[derive(Debug)]
+pub struct GitRequest {
+    pub name: String,
+    pub package: Option<String>,
+    pub member: String,
+    pub section: DependencySection,
+    pub spec: GitSpec,
+    pub registry: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitAnalysis {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub member: String,
+    pub section: DependencySection,
+    pub git: GitSpec,
+    pub pinned_commit: String,
+    /// The newest tag for tag pins, the followed branch otherwise.
+    pub target: String,
+    pub target_commit: String,
+    /// Commits of the target the pinned commit lacks; absent when following a branch
+    /// that `Cargo.lock` pins no commit of.
+    pub commits_behind: Option<usize>,
+    /// Newest release of the same crate on the registry, when it is published there.
+    pub registry_version: Option<String>,
+}
+
+impl GitAnalysis {
+    pub fn is_outdated(&self) -> bool {
+        self.commits_behind.is_some_and(|behind| behind > 0)
+    }
+}
+
+/// A `path` dependency whose `version` requirement rejects the local crate's version.
+#[derive(Debug, Serialize)]
+pub struct PathMismatch {
+    pub name: String,
+    pub member: String,
+    pub section: DependencySection,
+    pub path: String,
+    pub requirement: String,
+    pub local_version: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct DependencyAnalysis {
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub member: String,
+    pub manifest_path: PathBuf,
+    pub from_workspace: bool,
+    pub section: DependencySection,
     pub current_version: String,
+    /// Registry named by the dependency's `registry` key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Version `Cargo.lock` resolved the requirement to, when there is a lockfile.
+    pub locked_version: Option<String>,
+    /// Newest release the requirement already accepts.
+    pub compatible_version: Option<String>,
     pub latest_version: String,
-    pub is_outdated: bool,
+    pub status: UpdateStatus,
+    pub action: UpdateAction,
+    /// The exact version named by the requirement has been yanked from the registry.
+    pub pinned_yanked: bool,
+    /// Minimum Rust version declared by the latest release, when the registry says.
+    pub latest_rust_version: Option<String>,
+    /// Newest release of each semver-compatible line from the compatible version on.
+    #[serde(skip)]
+    pub candidates: Vec<String>,
+}
+
+/// A dependency that could not be analyzed, and why.
+#[derive(Debug, Serialize)]
+pub struct DependencyFailure {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub member: String,
+    pub section: DependencySection,
+    pub current_version: String,
+    pub error: &'static str,
+    pub reason: String,
+}
+
+impl DependencyFailure {
+    pub fn new(request: DependencyRequest, error: &TomieError) -> Self {
+        DependencyFailure {
+            name: request.name,
+            package: request.package,
+            member: request.member,
+            section: request.section,
+            current_version: request.current_version,
+            error: error.kind(),
+            reason: error.to_string(),
+        }
+    }
+
+    pub fn for_git(request: GitRequest, error: &TomieError) -> Self {
+        DependencyFailure {
+            name: request.name,
+            package: request.package,
+            member: request.member,
+            section: request.section,
+            current_version: request.spec.to_string(),
+            error: error.kind(),
+            reason: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AnalysisReport {
+    pub dependencies: Vec<DependencyAnalysis>,
+    pub git_dependencies: Vec<GitAnalysis>,
+    pub path_mismatches: Vec<PathMismatch>,
+    pub failures: Vec<DependencyFailure>,
+    pub lookups: LookupStats,
+    /// Seconds since cargo last refreshed the oldest local index entry used offline.
+    pub local_index_age: Option<u64>,
+}
+
+/// Registry lookups of a run; each crate is looked up once.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct LookupStats {
+    pub total: usize,
+    /// Lookups that needed more than one attempt.
+    pub retried: usize,
+    /// Lookups that still failed once out of attempts.
+    pub failed: usize,
+}
+
+impl AnalysisReport {
+    /// The most serious finding of the analysis.
+    pub fn severity(&self) -> Severity {
+        if !self.failures.is_empty() {
+            return Severity::Error;
+        }
+        // Like a breaking update, the local crate has moved past the requirement.
+        if !self.path_mismatches.is_empty() {
+            return Severity::Breaking;
+        }
+        // Whatever the requirement allows, a dependency already locked to the newest
+        // release needs nothing done.
+        let status = self
+            .dependencies
+            .iter()
+            .filter(|a| a.action != UpdateAction::None)
+            .map(|a| a.status)
+            .max();
+        match status {
+            Some(UpdateStatus::BreakingUpdate) => Severity::Breaking,
+            Some(UpdateStatus::CompatibleUpdate) => Severity::Outdated,
+            _ if self.git_dependencies.iter().any(GitAnalysis::is_outdated) => Severity::Outdated,
+            _ => Severity::UpToDate,
+        }
+    }
+}
+
+/// Outcome of a run, ordered from harmless to most serious.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    UpToDate,
+    Outdated,
+    Breaking,
+    Error,
+}
+
+impl Severity {
+    /// Process exit code reported when this severity fails the run.
+    /// 1 is left to fatal errors and 2 to command-line usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Severity::UpToDate => 0,
+            Severity::Outdated => 3,
+            Severity::Breaking => 4,
+            Severity::Error => 5,
+        }
+    }
+}
+
+/// Lowest severity that makes the process exit with a non-zero code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailOn {
+    /// Always exit with 0 once the analysis ran.
+    Never,
+    /// Any outdated dependency, compatible or not.
+    Outdated,
+    /// Only updates outside of the current requirements.
+    Breaking,
+    /// Only dependencies that could not be analyzed.
+    #[default]
+    Error,
+}
+
+impl FailOn {
+    pub fn exit_code(&self, severity: Severity) -> u8 {
+        let threshold = match self {
+            FailOn::Never => return 0,
+            FailOn::Outdated => Severity::Outdated,
+            FailOn::Breaking => Severity::Breaking,
+            FailOn::Error => Severity::Error,
+        };
+        if severity >= threshold {
+            severity.exit_code()
+        } else {
+            0
+        }
+    }
+}
+
+impl DependencyAnalysis {
+    pub fn is_outdated(&self) -> bool {
+        self.status != UpdateStatus::UpToDate
+    }
+
+    /// The version the dependency is on: the locked one, else the lowest its requirement accepts.
+    pub fn version_in_use(&self) -> Option<Version> {
+        if let Some(locked) = &self.locked_version {
+            return Version::parse(locked).ok();
+        }
+        requirement_floor(&VersionReq::parse(&self.current_version).ok()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateStatus {
+    /// The requirement already points at the newest release.
+    UpToDate,
+    /// A newer release exists and the requirement accepts it.
+    CompatibleUpdate,
+    /// The newest release falls outside the requirement.
+    BreakingUpdate,
+}
+
+/// What it takes to get the newest release of a dependency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateAction {
+    /// Nothing, the newest release is already in use.
+    None,
+    /// The requirement accepts a newer release than the one locked; `cargo update` picks it.
+    CargoUpdate,
+    /// The newest release needs a new requirement in `Cargo.toml`.
+    EditManifest,
+}
+
+impl UpdateAction {
+    pub fn new(
+        status: UpdateStatus,
+        locked: Option<&Version>,
+        compatible: Option<&Version>,
+    ) -> Self {
+        match (status, locked, compatible) {
+            (UpdateStatus::BreakingUpdate, _, _) => UpdateAction::EditManifest,
+            (_, Some(locked), Some(compatible)) if locked < compatible => UpdateAction::CargoUpdate,
+            (_, Some(_), _) => UpdateAction::None,
+            (UpdateStatus::CompatibleUpdate, None, _) => UpdateAction::CargoUpdate,
+            (UpdateStatus::UpToDate, None, _) => UpdateAction::None,
+        }
+    }
+}
+
+impl fmt::Display for UpdateStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            UpdateStatus::UpToDate => "up to date",
+            UpdateStatus::CompatibleUpdate => "compatible update",
+            UpdateStatus::BreakingUpdate => "breaking update",
+        };
+        f.write_str(label)
+    }
+}
+
+/// The lowest version `req` accepts, e.g. 1.2.0 for `^1.2` or `>=1.2, <2`.
+pub fn requirement_floor(req: &VersionReq) -> Option<Version> {
+    req.comparators
+        .iter()
+        .filter(|c| !matches!(c.op, Op::Less | Op::LessEq))
+        .map(|c| Version {
+            major: c.major,
+            minor: c.minor.unwrap_or(0),
+            patch: c.patch.unwrap_or(0),
+            pre: c.pre.clone(),
+            build: Default::default(),
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis(status: UpdateStatus, action: UpdateAction) -> DependencyAnalysis {
+        DependencyAnalysis {
+            name: "foo".to_string(),
+            package: None,
+            member: "demo".to_string(),
+            manifest_path: PathBuf::from("Cargo.toml"),
+            from_workspace: false,
+            section: DependencySection {
+                kind: DependencyKind::Normal,
+                target: None,
+            },
+            current_version: "1.0".to_string(),
+            registry: None,
+            locked_version: Some("1.4.0".to_string()),
+            compatible_version: Some("1.4.0".to_string()),
+            latest_version: "1.4.0".to_string(),
+            status,
+            action,
+            pinned_yanked: false,
+            latest_rust_version: None,
+            candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn fail_on_exits_from_its_threshold() {
+        let severities = [
+            Severity::UpToDate,
+            Severity::Outdated,
+            Severity::Breaking,
+            Severity::Error,
+        ];
+        let codes = |fail_on: FailOn| -> Vec<u8> {
+            severities.iter().map(|s| fail_on.exit_code(*s)).collect()
+        };
+        assert_eq!(codes(FailOn::Never), [0, 0, 0, 0]);
+        assert_eq!(codes(FailOn::Outdated), [0, 3, 4, 5]);
+        assert_eq!(codes(FailOn::Breaking), [0, 0, 4, 5]);
+        assert_eq!(codes(FailOn::Error), [0, 0, 0, 5]);
+    }
+
+    #[test]
+    fn severity_ignores_dependencies_locked_to_the_newest_release() {
+        let mut report = AnalysisReport {
+            dependencies: vec![analysis(UpdateStatus::CompatibleUpdate, UpdateAction::None)],
+            ..Default::default()
+        };
+        assert_eq!(report.severity(), Severity::UpToDate);
+
+        report.dependencies.push(analysis(
+            UpdateStatus::CompatibleUpdate,
+            UpdateAction::CargoUpdate,
+        ));
+        assert_eq!(report.severity(), Severity::Outdated);
+
+        report.dependencies.push(analysis(
+            UpdateStatus::BreakingUpdate,
+            UpdateAction::EditManifest,
+        ));
+        assert_eq!(report.severity(), Severity::Breaking);
+    }
 }
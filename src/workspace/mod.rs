@@ -0,0 +1,343 @@
+use crate::error::TomieError;
+use crate::utils::*;
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A crate whose manifest takes part in the analysis.
+#[derive(Debug)]
+pub struct Member {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    /// `package.version`, once resolved against `[workspace.package]`.
+    pub version: Option<String>,
+    pub sections: Vec<(DependencySection, DependencyTable)>,
+}
+
+impl Member {
+    fn dir(&self) -> &Path {
+        self.manifest_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+    }
+
+    /// Manifests of the crates this member depends on through `path` keys.
+    pub fn path_manifests(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.sections
+            .iter()
+            .flat_map(|(_, table)| table.values())
+            .filter_map(|dep| dep.path())
+            .map(|path| self.dir().join(path).join("Cargo.toml"))
+    }
+}
+
+/// The workspace members inherit from, and the directory of its root manifest.
+#[derive(Debug, Clone, Copy)]
+struct Root<'a> {
+    workspace: &'a Workspace,
+    dir: &'a Path,
+}
+
+fn read_manifest(path: &Path) -> Result<Tomie, TomieError> {
+    let content = fs::read_to_string(path).map_err(|source| TomieError::ManifestIo {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    toml::from_str(&content).map_err(|source| TomieError::TomlParse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn package_version(
+    manifest: &Tomie,
+    workspace_package: Option<&WorkspacePackage>,
+) -> Option<String> {
+    match manifest.package.as_ref()?.version.as_ref()? {
+        PackageVersion::Literal(version) => Some(version.clone()),
+        PackageVersion::Inherited { workspace: true } => workspace_package?.version.clone(),
+        PackageVersion::Inherited { workspace: false } => None,
+    }
+}
+
+fn member_name(manifest: &Tomie, manifest_path: &Path) -> String {
+    match &manifest.package {
+        Some(package) => package.name.clone(),
+        None => manifest_path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|| manifest_path.display().to_string()),
+    }
+}
+
+/// How to reach `root_dir` from `dir`, e.g. `../..` for a member in `crates/foo`;
+/// `root_dir` itself when `dir` is not below it.
+fn path_to_root(dir: &Path, root_dir: &Path) -> PathBuf {
+    match same_manifest(dir).strip_prefix(same_manifest(root_dir)) {
+        Ok(below) => below.components().map(|_| "..").collect(),
+        Err(_) => root_dir.to_path_buf(),
+    }
+}
+
+/// Replaces `dep = { workspace = true }` entries with the root `[workspace.dependencies]`
+/// entry, its `path` made relative to `dir`, the member's directory.
+fn resolve_inherited(
+    sections: &mut [(DependencySection, DependencyTable)],
+    root: Option<Root>,
+    dir: &Path,
+) {
+    let workspace_deps = root.and_then(|root| root.workspace.dependencies.as_ref());
+    let to_root = root.map_or_else(PathBuf::new, |root| path_to_root(dir, root.dir));
+    for (_, table) in sections.iter_mut() {
+        for (name, dep) in table.iter_mut() {
+            if !dep.is_workspace_inherited() {
+                continue;
+            }
+            match workspace_deps.and_then(|deps| deps.get(name)) {
+                Some(inherited) => *dep = inherited.inherited(&to_root),
+                None => log::warn!(
+                    "{} uses workspace = true but is missing from [workspace.dependencies]",
+                    name
+                ),
+            }
+        }
+    }
+}
+
+fn is_excluded(dir: &Path, root_dir: &Path, exclude: &[String]) -> bool {
+    exclude.iter().any(|pattern| {
+        let full = root_dir.join(pattern);
+        match glob::Pattern::new(&full.to_string_lossy()) {
+            Ok(pattern) => pattern.matches_path(dir) || dir.starts_with(&full),
+            Err(_) => dir.starts_with(&full),
+        }
+    })
+}
+
+fn member_manifests(root_dir: &Path, workspace: &Workspace) -> Result<Vec<PathBuf>, TomieError> {
+    let mut manifests = Vec::new();
+    for pattern in &workspace.members {
+        let full = root_dir.join(pattern);
+        let entries =
+            glob::glob(&full.to_string_lossy()).map_err(|source| TomieError::MemberPattern {
+                pattern: pattern.clone(),
+                source,
+            })?;
+        for entry in entries {
+            let dir = entry.map_err(|e| TomieError::ManifestIo {
+                path: e.path().to_path_buf(),
+                source: e.into(),
+            })?;
+            let manifest_path = dir.join("Cargo.toml");
+            if !manifest_path.is_file() || is_excluded(&dir, root_dir, &workspace.exclude) {
+                continue;
+            }
+            if !manifests.contains(&manifest_path) {
+                manifests.push(manifest_path);
+            }
+        }
+    }
+    Ok(manifests)
+}
+
+fn load_member(path: &Path, root: Option<Root>) -> Result<Member, TomieError> {
+    let manifest = read_manifest(path)?;
+    let name = member_name(&manifest, path);
+    let version = package_version(
+        &manifest,
+        root.and_then(|root| root.workspace.package.as_ref()),
+    );
+    let mut sections = manifest.into_sections();
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    resolve_inherited(&mut sections, root, dir);
+    Ok(Member {
+        name,
+        manifest_path: path.to_path_buf(),
+        version,
+        sections,
+    })
+}
+
+/// `path` dependencies whose `version` requirement no longer accepts the local crate.
+pub fn path_mismatches(members: &[Member]) -> Vec<PathMismatch> {
+    let versions: HashMap<PathBuf, &str> = members
+        .iter()
+        .filter_map(|m| Some((same_manifest(&m.manifest_path), m.version.as_deref()?)))
+        .collect();
+
+    let mut mismatches = Vec::new();
+    for member in members {
+        for (section, table) in &member.sections {
+            for (name, dep) in table {
+                let (Some(path), Some(requirement)) = (dep.path(), dep.version()) else {
+                    continue;
+                };
+                let manifest = same_manifest(&member.dir().join(path).join("Cargo.toml"));
+                let Some(local_version) = versions.get(&manifest) else {
+                    continue;
+                };
+                let matches = match (
+                    VersionReq::parse(requirement),
+                    Version::parse(local_version),
+                ) {
+                    (Ok(req), Ok(version)) => req.matches(&version),
+                    _ => {
+                        log::warn!(
+                            "Unable to compare {} {} with the local version {}",
+                            name,
+                            requirement,
+                            local_version
+                        );
+                        continue;
+                    }
+                };
+                if !matches {
+                    mismatches.push(PathMismatch {
+                        name: name.clone(),
+                        member: member.name.clone(),
+                        section: section.clone(),
+                        path: path.to_string(),
+                        requirement: requirement.to_string(),
+                        local_version: local_version.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    mismatches
+}
+
+/// Same file, however the path to it was spelled.
+fn same_manifest(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Adds the crates reached through `path` dependencies, recursively, unless already
+/// loaded; only those below the workspace root inherit from the workspace.
+fn follow_path_dependencies(members: &mut Vec<Member>, root: Option<Root>) {
+    let root_dir = root.map(|root| same_manifest(root.dir));
+    let mut seen: HashSet<PathBuf> = members
+        .iter()
+        .map(|m| same_manifest(&m.manifest_path))
+        .collect();
+    let mut next = 0;
+    while next < members.len() {
+        let manifests: Vec<PathBuf> = members[next].path_manifests().collect();
+        for path in manifests {
+            let path = same_manifest(&path);
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let root = root.filter(|_| {
+                root_dir
+                    .as_ref()
+                    .is_some_and(|root_dir| path.starts_with(root_dir))
+            });
+            match load_member(&path, root) {
+                Ok(member) => {
+                    log::info!("Path dependency found: {}", path.display());
+                    members.push(member);
+                }
+                Err(e) => log::warn!("Skipping path dependency: {}", e),
+            }
+        }
+        next += 1;
+    }
+}
+
+/// Loads the manifest at `manifest_path`, every member manifest of a workspace root,
+/// and the local crates they reach through `path` dependencies.
+pub fn load_members(manifest_path: &Path) -> Result<Vec<Member>, TomieError> {
+    let root = read_manifest(manifest_path)?;
+    let root_dir = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    let Some(workspace) = &root.workspace else {
+        let mut members = vec![load_member(manifest_path, None)?];
+        follow_path_dependencies(&mut members, None);
+        return Ok(members);
+    };
+
+    let workspace_root = Root {
+        workspace,
+        dir: root_dir,
+    };
+    let mut members = Vec::new();
+    // A root manifest with a [package] is a member of its own workspace.
+    if root.package.is_some() {
+        members.push(load_member(manifest_path, Some(workspace_root))?);
+    }
+    for path in member_manifests(root_dir, workspace)? {
+        log::info!("Workspace member found: {}", path.display());
+        members.push(load_member(&path, Some(workspace_root))?);
+    }
+
+    follow_path_dependencies(&mut members, Some(workspace_root));
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn inherited_path_dependencies_are_relative_to_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        write(
+            &root,
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["crates/bar"]
+
+            [workspace.package]
+            version = "0.2.0"
+
+            [workspace.dependencies]
+            foo = { path = "crates/foo", version = "0.1" }
+            ext = { path = "../ext", version = "1" }
+            "#,
+        );
+        write(
+            &root,
+            "crates/bar/Cargo.toml",
+            "[package]\nname = \"bar\"\nversion = \"0.1.0\"\n\n[dependencies]\nfoo.workspace = true\next.workspace = true\n",
+        );
+        write(
+            &root,
+            "crates/foo/Cargo.toml",
+            "[package]\nname = \"foo\"\nversion.workspace = true\n",
+        );
+        // Outside the workspace, so it does not inherit its package version.
+        write(
+            dir.path(),
+            "ext/Cargo.toml",
+            "[package]\nname = \"ext\"\nversion.workspace = true\n",
+        );
+
+        let members = load_members(&root.join("Cargo.toml")).unwrap();
+        let names: Vec<&str> = members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["bar", "ext", "foo"]);
+        assert_eq!(members[1].version, None);
+        assert_eq!(members[2].version.as_deref(), Some("0.2.0"));
+
+        let mismatches = path_mismatches(&members);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].name, "foo");
+        assert_eq!(mismatches[0].member, "bar");
+        assert_eq!(mismatches[0].path, "../../crates/foo");
+        assert_eq!(mismatches[0].requirement, "0.1");
+        assert_eq!(mismatches[0].local_version, "0.2.0");
+    }
+}